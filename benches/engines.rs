@@ -0,0 +1,46 @@
+//! Hot-loop benchmarks for both execution engines.
+//! Run with `cargo bench --bench engines`.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use rust_basic::console::CapturedOutput;
+use rust_basic::{Engine, Interpreter, Program};
+
+const PROGRAMS: &[(&str, &str)] = &[
+    (
+        // Sums I mod 7, which stays well inside i32
+        "sum of remainders",
+        "10 LET I = 1\n20 LET S = S + I - I / 7 * 7\n30 LET I = I + 1\n40 IF I <= 20000 THEN 20\n50 PRINT S\n",
+    ),
+    (
+        "array fill",
+        "10 DIM A(100)\n20 LET N = 0\n30 LET I = 0\n40 LET A(I) = A(I) + I * N\n50 LET I = I + 1\n\
+         60 IF I < 100 THEN 40\n70 LET N = N + 1\n80 IF N < 100 THEN 30\n90 PRINT A(99)\n",
+    ),
+    (
+        // Jumps across many lines, where finding the target used to dominate
+        "long jumps",
+        "10 LET I = 0\n20 GOTO 1000\n30 IF I < 5000 THEN 20\n40 END\n\
+         1000 LET I = I + 1\n1010 GOTO 30\n",
+    ),
+];
+
+fn engines(c: &mut Criterion) {
+    for (name, source) in PROGRAMS {
+        let program = Program::parse(source).unwrap();
+        let mut group = c.benchmark_group(*name);
+        for (label, engine) in [("tree-walker", Engine::TreeWalker), ("bytecode", Engine::Bytecode)] {
+            group.bench_with_input(BenchmarkId::from_parameter(label), &program, |b, program| {
+                b.iter(|| {
+                    let mut interp = Interpreter::new(program.clone());
+                    interp.set_output(CapturedOutput::new());
+                    interp.set_engine(engine);
+                    interp.run().unwrap()
+                })
+            });
+        }
+        group.finish();
+    }
+}
+
+criterion_group!(benches, engines);
+criterion_main!(benches);
@@ -1,10 +1,20 @@
 /// Abstract Syntax Tree for Tiny BASIC programs
 
 #[derive(Debug, Clone)]
+#[non_exhaustive]
 pub enum Expr {
     Number(i32),
     Variable(char),
     ArrayAccess(char, Box<Expr>),
+    Neg(Box<Expr>),
+    /// Number of arguments passed on the command line
+    Argc,
+    /// Command-line argument by 1-based position
+    Argv(Box<Expr>),
+    /// String literal; only meaningful as a native function argument
+    Str(String),
+    /// Call of a host-registered native function
+    Call(String, Vec<Expr>),
     Binary {
         left: Box<Expr>,
         op: BinaryOp,
@@ -12,7 +22,24 @@ pub enum Expr {
     },
 }
 
+impl Expr {
+    /// Call `f` on this expression and every subexpression, outermost first
+    pub fn visit(&self, f: &mut impl FnMut(&Expr)) {
+        f(self);
+        match self {
+            Expr::ArrayAccess(_, e) | Expr::Neg(e) | Expr::Argv(e) => e.visit(f),
+            Expr::Call(_, args) => args.iter().for_each(|a| a.visit(f)),
+            Expr::Binary { left, right, .. } => {
+                left.visit(f);
+                right.visit(f);
+            }
+            Expr::Number(_) | Expr::Variable(_) | Expr::Argc | Expr::Str(_) => {}
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
 pub enum BinaryOp {
     Add,
     Sub,
@@ -27,8 +54,13 @@ pub enum BinaryOp {
 }
 
 #[derive(Debug, Clone)]
+#[non_exhaustive]
 pub enum Stmt {
     Print(Vec<PrintItem>),
+    Input {
+        prompt: Option<String>,
+        vars: Vec<char>,
+    },
     Let(char, Box<Expr>),
     LetArray(char, Box<Expr>, Box<Expr>),
     Goto(i32),
@@ -37,10 +69,66 @@ pub enum Stmt {
         then_line: i32,
     },
     End,
+    Stop,
     Dim(char, i32),
 }
 
+impl Stmt {
+    /// Line numbers this statement can jump to
+    pub fn line_refs(&self) -> Vec<i32> {
+        match self {
+            Stmt::Goto(n) => vec![*n],
+            Stmt::If { then_line, .. } => vec![*then_line],
+            _ => Vec::new(),
+        }
+    }
+
+    /// The expressions this statement evaluates, in evaluation order
+    pub fn exprs(&self) -> Vec<&Expr> {
+        match self {
+            Stmt::Print(items) => items
+                .iter()
+                .filter_map(|item| match item {
+                    PrintItem::Expr(e) => Some(e),
+                    PrintItem::String(_) => None,
+                })
+                .collect(),
+            Stmt::Let(_, value) => vec![value],
+            Stmt::LetArray(_, index, value) => vec![index, value],
+            Stmt::If { condition, .. } => vec![condition],
+            Stmt::Input { .. } | Stmt::Goto(_) | Stmt::End | Stmt::Stop | Stmt::Dim(..) => Vec::new(),
+        }
+    }
+
+    /// Mutable access to the expressions this statement evaluates
+    pub fn exprs_mut(&mut self) -> Vec<&mut Expr> {
+        match self {
+            Stmt::Print(items) => items
+                .iter_mut()
+                .filter_map(|item| match item {
+                    PrintItem::Expr(e) => Some(e),
+                    PrintItem::String(_) => None,
+                })
+                .collect(),
+            Stmt::Let(_, value) => vec![value],
+            Stmt::LetArray(_, index, value) => vec![index, value],
+            Stmt::If { condition, .. } => vec![condition],
+            Stmt::Input { .. } | Stmt::Goto(_) | Stmt::End | Stmt::Stop | Stmt::Dim(..) => Vec::new(),
+        }
+    }
+
+    /// Mutable access to the line numbers this statement can jump to
+    pub fn line_refs_mut(&mut self) -> Vec<&mut i32> {
+        match self {
+            Stmt::Goto(n) => vec![n],
+            Stmt::If { then_line, .. } => vec![then_line],
+            _ => Vec::new(),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
+#[non_exhaustive]
 pub enum PrintItem {
     Expr(Expr),
     String(String),
@@ -0,0 +1,162 @@
+/// Bytecode for Tiny BASIC - a compact form of the program for the stack VM
+///
+/// Each program line compiles to a `Line` marker followed by stack code for
+/// its statement. Jumps are resolved to instruction offsets up front, so the
+/// VM never searches for a line number. Statements that are dominated by
+/// I/O (PRINT, INPUT, DIM) and native function calls are not worth compiling
+/// and are handed back to the tree-walker through `Exec` and `Eval`.
+use crate::ast::{BinaryOp, Expr, Line, Stmt};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Op {
+    /// Start of the program line with this index: statement accounting,
+    /// line hooks and cancellation happen here
+    Line(usize),
+    Push(i32),
+    Load(char),
+    /// Pop an index and push that element of the array
+    LoadElement(char),
+    /// Pop a value into a variable
+    Store(char),
+    /// Pop a value, then an index, and store the value in the array
+    StoreElement(char),
+    Argc,
+    /// Pop a 1-based index and push that command-line argument
+    Argv,
+    Neg,
+    /// Pop the right then the left operand and push the result
+    Binary(BinaryOp),
+    /// Evaluate the expression with this index in `Code::exprs` using the
+    /// tree-walker and push the result
+    Eval(usize),
+    /// Execute the statement with this index in `Code::stmts` using the
+    /// tree-walker
+    Exec(usize),
+    Jump(usize),
+    /// Pop a condition and jump if it is non-zero
+    JumpIf(usize),
+    /// A jump to a line that doesn't exist
+    Fail(i32),
+    Stop,
+    End,
+    /// Ran off the last line
+    Halt,
+}
+
+/// A compiled program
+#[derive(Debug, Clone, Default)]
+pub struct Code {
+    pub ops: Vec<Op>,
+    /// Offset of each program line's `Line` op, by line index
+    pub line_starts: Vec<usize>,
+    pub exprs: Vec<Expr>,
+    pub stmts: Vec<Stmt>,
+}
+
+struct Compiler {
+    code: Code,
+    /// Jumps still waiting for their target's offset: (op offset, line number)
+    fixups: Vec<(usize, i32)>,
+}
+
+/// Compile program lines, which must be sorted by number as in `Program`
+pub fn compile(lines: &[Line]) -> Code {
+    let mut compiler = Compiler {
+        code: Code::default(),
+        fixups: Vec::new(),
+    };
+    for (index, line) in lines.iter().enumerate() {
+        compiler.code.line_starts.push(compiler.code.ops.len());
+        compiler.code.ops.push(Op::Line(index));
+        compiler.statement(&line.stmt);
+    }
+    compiler.code.ops.push(Op::Halt);
+
+    let offsets: HashMap<i32, usize> = lines
+        .iter()
+        .zip(&compiler.code.line_starts)
+        .map(|(line, &start)| (line.number, start))
+        .collect();
+    // Missing targets jump to a `Fail` op that reports the bad line number
+    let mut failures = HashMap::new();
+    for (at, target) in std::mem::take(&mut compiler.fixups) {
+        let offset = match offsets.get(&target) {
+            Some(&offset) => offset,
+            None => *failures.entry(target).or_insert_with(|| {
+                compiler.code.ops.push(Op::Fail(target));
+                compiler.code.ops.len() - 1
+            }),
+        };
+        match &mut compiler.code.ops[at] {
+            Op::Jump(to) | Op::JumpIf(to) => *to = offset,
+            _ => unreachable!("fixup for a non-jump op"),
+        }
+    }
+    compiler.code
+}
+
+impl Compiler {
+    fn emit(&mut self, op: Op) {
+        self.code.ops.push(op);
+    }
+
+    fn jump(&mut self, op: Op, target: i32) {
+        self.fixups.push((self.code.ops.len(), target));
+        self.emit(op);
+    }
+
+    fn statement(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::Let(name, value) => {
+                self.expr(value);
+                self.emit(Op::Store(*name));
+            }
+            Stmt::LetArray(name, index, value) => {
+                self.expr(index);
+                self.expr(value);
+                self.emit(Op::StoreElement(*name));
+            }
+            Stmt::Goto(target) => self.jump(Op::Jump(0), *target),
+            Stmt::If { condition, then_line } => {
+                self.expr(condition);
+                self.jump(Op::JumpIf(0), *then_line);
+            }
+            Stmt::End => self.emit(Op::End),
+            Stmt::Stop => self.emit(Op::Stop),
+            Stmt::Print(_) | Stmt::Input { .. } | Stmt::Dim(..) => {
+                self.code.stmts.push(stmt.clone());
+                self.emit(Op::Exec(self.code.stmts.len() - 1));
+            }
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Number(n) => self.emit(Op::Push(*n)),
+            Expr::Variable(name) => self.emit(Op::Load(*name)),
+            Expr::ArrayAccess(name, index) => {
+                self.expr(index);
+                self.emit(Op::LoadElement(*name));
+            }
+            Expr::Neg(operand) => {
+                self.expr(operand);
+                self.emit(Op::Neg);
+            }
+            Expr::Argc => self.emit(Op::Argc),
+            Expr::Argv(index) => {
+                self.expr(index);
+                self.emit(Op::Argv);
+            }
+            Expr::Binary { left, op, right } => {
+                self.expr(left);
+                self.expr(right);
+                self.emit(Op::Binary(*op));
+            }
+            Expr::Str(_) | Expr::Call(..) => {
+                self.code.exprs.push(expr.clone());
+                self.emit(Op::Eval(self.code.exprs.len() - 1));
+            }
+        }
+    }
+}
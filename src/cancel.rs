@@ -0,0 +1,50 @@
+//! Cancellation for Tiny BASIC - stopping a running program from outside
+//!
+//! The interpreter checks its token between statements, so cancelling takes
+//! effect before the next line runs and `run`/`cont` return
+//! `RunStatus::Interrupted(line)`. The program can then be resumed with
+//! `cont`. Tokens are cheap to clone and can be cancelled from any thread
+//! or from a signal handler.
+//!
+//! ```
+//! use rust_basic::{CancelToken, Interpreter, Program, RunStatus};
+//!
+//! let mut interp = Interpreter::new(Program::parse("10 GOTO 10\n").unwrap());
+//! let token = interp.cancel_token();
+//! let worker = std::thread::spawn(move || interp.run());
+//! token.cancel();
+//! assert_eq!(worker.join().unwrap().unwrap(), RunStatus::Interrupted(10));
+//! ```
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ask the program to stop before its next statement
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Withdraw a pending cancellation, e.g. a Ctrl-C pressed at the prompt
+    pub fn reset(&self) {
+        self.cancelled.store(false, Ordering::SeqCst);
+    }
+
+    /// Consume a pending cancellation, returning whether there was one
+    pub(crate) fn take(&self) -> bool {
+        self.cancelled.swap(false, Ordering::SeqCst)
+    }
+}
@@ -0,0 +1,287 @@
+/// Command-line interface for running BASIC programs without the REPL
+use rust_basic::{
+    format_line, lint, lints_to_json, parse_immediate, parse_recovering, Engine, Interpreter, Limits, Program,
+    RunError, RunStatus, RuntimeError,
+};
+use std::fs;
+use std::io::{self, Read};
+use std::time::Duration;
+
+/// The program ran to END (or off its last line)
+pub const EXIT_OK: i32 = 0;
+/// The program stopped with a runtime error, or `lint` found problems
+pub const EXIT_RUNTIME_ERROR: i32 = 1;
+/// The program failed to parse or jumps to a missing line
+pub const EXIT_PARSE_ERROR: i32 = 2;
+/// Bad command line or unreadable file
+pub const EXIT_USAGE: i32 = 3;
+/// The program reached STOP; unlike at the REPL it can't be continued, so
+/// it didn't finish
+pub const EXIT_STOPPED: i32 = 4;
+/// The program was stopped with Ctrl-C (128 + SIGINT, as shells report it)
+pub const EXIT_INTERRUPTED: i32 = 130;
+
+const USAGE: &str = "\
+Usage: rust-basic                        start the REPL, or run a program piped to stdin
+       rust-basic repl                   start the REPL even when stdin is not a terminal
+       rust-basic [run] FILE [ARGS...]   run a program (FILE `-` reads stdin);
+                                         ARGS are read with ARGC and ARGV(n)
+       rust-basic run [OPTIONS] FILE ... run with options:
+                                         --engine tree|bytecode (default tree),
+                                         --optimize to fold constant expressions,
+                                         and limits for untrusted programs:
+                                         --max-statements N, --timeout-ms N,
+                                         --max-array-bytes N, --max-string-len N
+       rust-basic check FILE             parse and check a program without running it
+       rust-basic lint [--json] FILE     warn about likely mistakes, optionally as JSON
+       rust-basic list FILE              print a program in canonical form
+       rust-basic -e STATEMENT           execute one statement, e.g. -e 'PRINT 1+1'";
+
+/// Run the subcommand named by `args` (program name excluded) and return
+/// the process exit code
+pub fn main(args: &[String]) -> i32 {
+    match args.first().map(String::as_str) {
+        Some("run") => match parse_run_options(&args[1..]) {
+            Some((options, [path, rest @ ..])) => run_file(path, rest, options),
+            _ => {
+                eprintln!("{}", USAGE);
+                EXIT_USAGE
+            }
+        },
+        Some("check") if args.len() == 2 => check_file(&args[1]),
+        Some("lint") if args.len() == 2 => lint_file(&args[1], false),
+        Some("lint") if args.len() == 3 && args[1] == "--json" => lint_file(&args[2], true),
+        Some("list") if args.len() == 2 => list_file(&args[1]),
+        Some("-e") if args.len() == 2 => execute(&args[1]),
+        Some("-h") | Some("--help") => {
+            println!("{}", USAGE);
+            EXIT_OK
+        }
+        // A bare file name, as passed by a `#!/usr/bin/env rust-basic` line
+        Some(path) if path == "-" || !path.starts_with('-') => run_file(path, &args[1..], RunOptions::default()),
+        _ => {
+            eprintln!("{}", USAGE);
+            EXIT_USAGE
+        }
+    }
+}
+
+/// How `run` executes a program, from its command-line options
+#[derive(Default)]
+struct RunOptions {
+    limits: Limits,
+    engine: Engine,
+    optimize: bool,
+}
+
+/// Strip leading options, returning them and the rest. All take a value
+/// except `--optimize`.
+fn parse_run_options(mut args: &[String]) -> Option<(RunOptions, &[String])> {
+    let mut options = RunOptions::default();
+    while let [option, rest @ ..] = args {
+        if option == "--optimize" {
+            options.optimize = true;
+            args = rest;
+            continue;
+        }
+        let [value, rest @ ..] = rest else {
+            break;
+        };
+        let limits = &mut options.limits;
+        match option.as_str() {
+            "--engine" => {
+                options.engine = match value.as_str() {
+                    "tree" => Engine::TreeWalker,
+                    "bytecode" => Engine::Bytecode,
+                    _ => return None,
+                }
+            }
+            "--max-statements" => limits.max_statements = Some(value.parse().ok()?),
+            "--timeout-ms" => limits.timeout = Some(Duration::from_millis(value.parse().ok()?)),
+            "--max-array-bytes" => limits.max_array_bytes = Some(value.parse().ok()?),
+            "--max-string-len" => limits.max_string_len = Some(value.parse().ok()?),
+            _ if option.starts_with("--") => return None,
+            _ => break,
+        }
+        args = rest;
+    }
+    Some((options, args))
+}
+
+/// Read a program from a file, or from stdin when `path` is `-`
+fn read_source(path: &str) -> io::Result<String> {
+    if path == "-" {
+        let mut source = String::new();
+        io::stdin().read_to_string(&mut source)?;
+        Ok(source)
+    } else {
+        fs::read_to_string(path)
+    }
+}
+
+/// Parse a whole program, reporting every broken line on stderr
+fn parse_program(path: &str, source: &str) -> Result<Program, i32> {
+    let (lines, broken) = parse_recovering(source);
+    for b in &broken {
+        eprintln!("{}", b.error.report(Some(path), source));
+    }
+    if broken.is_empty() {
+        Ok(Program::new(lines))
+    } else {
+        Err(EXIT_PARSE_ERROR)
+    }
+}
+
+/// Load, parse and link a program file, reporting problems on stderr
+fn load(path: &str, optimize: bool) -> Result<Interpreter, i32> {
+    let source = read_source(path).map_err(|e| {
+        eprintln!("{}: {}", path, e);
+        EXIT_USAGE
+    })?;
+    let mut program = parse_program(path, &source)?;
+    if optimize {
+        program = rust_basic::optimize(&program);
+    }
+    let interp = Interpreter::new(program);
+    interp.link().map_err(|errors| {
+        for e in errors {
+            eprintln!("{}: Link error: {}", path, e);
+        }
+        EXIT_PARSE_ERROR
+    })?;
+    Ok(interp)
+}
+
+fn run_file(path: &str, args: &[String], options: RunOptions) -> i32 {
+    let mut program_args = Vec::new();
+    for arg in args {
+        match arg.parse::<i32>() {
+            Ok(n) => program_args.push(n),
+            Err(_) => {
+                eprintln!("Program arguments must be integers, got '{}'", arg);
+                return EXIT_USAGE;
+            }
+        }
+    }
+
+    let mut interp = match load(path, options.optimize) {
+        Ok(interp) => interp,
+        Err(code) => return code,
+    };
+    interp.set_args(program_args);
+    interp.set_limits(options.limits);
+    interp.set_engine(options.engine);
+    // Let Ctrl-C stop the program between statements so it can report
+    // where it was, instead of killing the process mid-PRINT
+    let token = interp.cancel_token();
+    if let Err(e) = ctrlc::set_handler(move || token.cancel()) {
+        eprintln!("Warning: could not install Ctrl-C handler: {}", e);
+    }
+    finish(interp.run())
+}
+
+fn check_file(path: &str) -> i32 {
+    match load(path, false) {
+        Ok(_) => {
+            println!("{}: OK", path);
+            EXIT_OK
+        }
+        Err(code) => code,
+    }
+}
+
+fn lint_file(path: &str, json: bool) -> i32 {
+    let source = match read_source(path) {
+        Ok(source) => source,
+        Err(e) => {
+            eprintln!("{}: {}", path, e);
+            return EXIT_USAGE;
+        }
+    };
+    let program = match parse_program(path, &source) {
+        Ok(program) => program,
+        Err(code) => return code,
+    };
+    let lints = lint(&program);
+    if json {
+        println!("{}", lints_to_json(&lints));
+    } else {
+        for l in &lints {
+            println!("{}: {}", path, l);
+        }
+    }
+    if lints.is_empty() {
+        EXIT_OK
+    } else {
+        EXIT_RUNTIME_ERROR
+    }
+}
+
+fn list_file(path: &str) -> i32 {
+    let source = match read_source(path) {
+        Ok(source) => source,
+        Err(e) => {
+            eprintln!("{}: {}", path, e);
+            return EXIT_USAGE;
+        }
+    };
+    match parse_program(path, &source) {
+        Ok(program) => {
+            for line in program.lines() {
+                println!("{}", format_line(line));
+            }
+            EXIT_OK
+        }
+        Err(code) => code,
+    }
+}
+
+/// Execute a single statement as if typed at the REPL prompt
+fn execute(source: &str) -> i32 {
+    let stmt = match parse_immediate(source) {
+        Ok(stmt) => stmt,
+        Err(e) => {
+            eprintln!("Parse error: {}", e.report(None, source));
+            return EXIT_PARSE_ERROR;
+        }
+    };
+    let mut interp = Interpreter::new(Program::default());
+    match interp.execute_immediate(&stmt) {
+        Ok(None) => EXIT_OK,
+        Ok(Some(target)) => {
+            eprintln!("Runtime error: {}", RuntimeError::InvalidLineNumber(target));
+            EXIT_RUNTIME_ERROR
+        }
+        Err(e) => {
+            eprintln!("Runtime error: {}", e);
+            EXIT_RUNTIME_ERROR
+        }
+    }
+}
+
+fn finish(result: Result<RunStatus, RunError>) -> i32 {
+    match result {
+        Ok(RunStatus::Ended) => EXIT_OK,
+        Ok(RunStatus::Stopped(n)) => {
+            eprintln!("Stopped at line {}", n);
+            EXIT_STOPPED
+        }
+        Ok(RunStatus::Interrupted(n)) => {
+            eprintln!("Break in {}", n);
+            EXIT_INTERRUPTED
+        }
+        Ok(RunStatus::WaitingForInput(n)) => {
+            eprintln!("No input available in line {}", n);
+            EXIT_RUNTIME_ERROR
+        }
+        // Any other pause means the program didn't finish
+        Ok(status) => {
+            eprintln!("{}", status);
+            EXIT_RUNTIME_ERROR
+        }
+        Err(e) => {
+            eprintln!("Runtime error: {}", e);
+            EXIT_RUNTIME_ERROR
+        }
+    }
+}
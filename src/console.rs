@@ -0,0 +1,121 @@
+/// Console I/O for Tiny BASIC - where PRINT writes and INPUT reads
+///
+/// The interpreter talks to the outside world only through the `Output`
+/// and `Input` traits, so a host can send output to a GUI, buffer it, or
+/// feed INPUT from a script. `Stdout` and `Stdin` are the defaults.
+use std::collections::VecDeque;
+use std::io::{self, BufRead, Write};
+use std::sync::{Arc, Mutex};
+
+/// Destination for PRINT output
+pub trait Output: Send {
+    /// Write one line of output; the line ending is added by the implementation
+    fn write_line(&mut self, text: &str) -> io::Result<()>;
+}
+
+/// Source of values for INPUT
+pub trait Input: Send {
+    /// Show `prompt` and read one line, without its line ending. `Ok(None)`
+    /// means there is no more input.
+    fn read_line(&mut self, prompt: &str) -> io::Result<Option<String>>;
+}
+
+/// Writes to the process's standard output
+pub struct Stdout;
+
+impl Output for Stdout {
+    fn write_line(&mut self, text: &str) -> io::Result<()> {
+        writeln!(io::stdout().lock(), "{}", text)
+    }
+}
+
+/// Prompts on standard output and reads from standard input
+pub struct Stdin;
+
+impl Input for Stdin {
+    fn read_line(&mut self, prompt: &str) -> io::Result<Option<String>> {
+        let mut stdout = io::stdout().lock();
+        write!(stdout, "{}", prompt)?;
+        stdout.flush()?;
+
+        let mut line = String::new();
+        if io::stdin().lock().read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        Ok(Some(line.trim_end_matches(['\r', '\n']).to_string()))
+    }
+}
+
+/// Collects output in memory. Clones share the same buffer, so a host can
+/// keep one clone and hand the other to the interpreter.
+#[derive(Clone, Default)]
+pub struct CapturedOutput {
+    buffer: Arc<Mutex<String>>,
+}
+
+impl CapturedOutput {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Everything written so far, one `\n`-terminated line per PRINT
+    pub fn contents(&self) -> String {
+        self.buffer.lock().unwrap().clone()
+    }
+}
+
+impl Output for CapturedOutput {
+    fn write_line(&mut self, text: &str) -> io::Result<()> {
+        let mut buffer = self.buffer.lock().unwrap();
+        buffer.push_str(text);
+        buffer.push('\n');
+        Ok(())
+    }
+}
+
+/// Input a host supplies as it arrives, e.g. from a GUI text box. While the
+/// queue is empty INPUT reports `WouldBlock`, so `Interpreter::step` returns
+/// `StepStatus::WaitingForInput` instead of blocking. Clones share the queue.
+#[derive(Clone, Default)]
+pub struct InputQueue {
+    lines: Arc<Mutex<VecDeque<String>>>,
+}
+
+impl InputQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push_line(&self, line: impl Into<String>) {
+        self.lines.lock().unwrap().push_back(line.into());
+    }
+}
+
+impl Input for InputQueue {
+    fn read_line(&mut self, _prompt: &str) -> io::Result<Option<String>> {
+        match self.lines.lock().unwrap().pop_front() {
+            Some(line) => Ok(Some(line)),
+            None => Err(io::ErrorKind::WouldBlock.into()),
+        }
+    }
+}
+
+/// Answers INPUT from a fixed list of lines, ignoring prompts
+#[derive(Clone, Default)]
+pub struct ScriptedInput {
+    lines: VecDeque<String>,
+}
+
+impl ScriptedInput {
+    pub fn new<S: Into<String>>(lines: impl IntoIterator<Item = S>) -> Self {
+        Self {
+            lines: lines.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl Input for ScriptedInput {
+    fn read_line(&mut self, _prompt: &str) -> io::Result<Option<String>> {
+        Ok(self.lines.pop_front())
+    }
+}
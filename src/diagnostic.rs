@@ -0,0 +1,36 @@
+/// Diagnostics for Tiny BASIC - messages that point into the source
+///
+/// A diagnostic names its location as `file:line:col`, then repeats the
+/// source line with a caret under the offending text:
+///
+/// ```text
+/// prog.bas:3:10: Expected '=', found number 5
+///  3 | 30 LET X 5
+///    |          ^
+/// ```
+use crate::lexer::Span;
+
+/// Format `message` for the text at `span` in `source`. Without a file name
+/// (e.g. for a line typed at the REPL) the location prefix is left out.
+pub fn render(file: Option<&str>, source: &str, span: Span, message: &str) -> String {
+    let mut out = match file {
+        Some(file) => format!("{}:{}:{}: {}", file, span.line, span.column, message),
+        None => message.to_string(),
+    };
+    if let Some(text) = source.lines().nth(span.line.saturating_sub(1)) {
+        let gutter = span.line.to_string();
+        let indent: String = text
+            .chars()
+            .take(span.column.saturating_sub(1))
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        out.push_str(&format!("\n {} | {}", gutter, text));
+        out.push_str(&format!(
+            "\n {} | {}{}",
+            " ".repeat(gutter.len()),
+            indent,
+            "^".repeat(span.len.max(1))
+        ));
+    }
+    out
+}
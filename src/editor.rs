@@ -0,0 +1,138 @@
+/// Line editor for the REPL - history, keyword completion and pre-filled input
+use rustyline::completion::Completer;
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::DefaultHistory;
+use rustyline::validate::Validator;
+use rustyline::{Context, Editor, Helper};
+use std::io::{self, IsTerminal};
+use std::path::PathBuf;
+
+/// Words offered by tab completion: statements first, then REPL commands
+const COMPLETIONS: &[&str] = &[
+    "PRINT", "INPUT", "LET", "GOTO", "IF", "THEN", "END", "STOP", "DIM", "ARGC", "ARGV", "LOAD", "SAVE", "RUN",
+    "CONT", "CHECK", "LIST", "FIND", "EDIT", "DELETE", "RENUM", "AUTO", "NEW", "QUIT",
+];
+
+const HISTORY_FILE: &str = ".rust_basic_history";
+
+/// What the user did at a prompt
+pub enum Input {
+    Line(String),
+    /// Ctrl-C
+    Interrupted,
+    /// Ctrl-D or end of piped input
+    Eof,
+}
+
+struct BasicHelper;
+
+impl Completer for BasicHelper {
+    type Candidate = String;
+
+    fn complete(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> rustyline::Result<(usize, Vec<String>)> {
+        Ok(complete_word(line, pos))
+    }
+}
+
+/// Keywords completing the word that ends at byte `pos`, and where that
+/// word starts
+fn complete_word(line: &str, pos: usize) -> (usize, Vec<String>) {
+    // The character before the word may be more than one byte, e.g. in a string
+    let start = line[..pos]
+        .char_indices()
+        .rev()
+        .find(|(_, c)| !c.is_ascii_alphabetic())
+        .map_or(0, |(i, c)| i + c.len_utf8());
+    let word = line[start..pos].to_ascii_uppercase();
+    if word.is_empty() {
+        return (pos, Vec::new());
+    }
+    let candidates = COMPLETIONS
+        .iter()
+        .filter(|kw| kw.starts_with(&word))
+        .map(|kw| kw.to_string())
+        .collect();
+    (start, candidates)
+}
+
+impl Hinter for BasicHelper {
+    type Hint = String;
+}
+
+impl Highlighter for BasicHelper {}
+
+impl Validator for BasicHelper {}
+
+impl Helper for BasicHelper {}
+
+pub struct LineEditor {
+    editor: Editor<BasicHelper, DefaultHistory>,
+    history_path: Option<PathBuf>,
+}
+
+impl LineEditor {
+    /// Create an editor. History is loaded from (and saved to) a dotfile in
+    /// the home directory, but only for interactive sessions so that piped
+    /// input never pollutes it.
+    pub fn new() -> Self {
+        let mut editor = Editor::new().expect("failed to initialise line editor");
+        editor.set_helper(Some(BasicHelper));
+
+        let history_path = std::env::var_os("HOME")
+            .filter(|_| io::stdin().is_terminal())
+            .map(|home| PathBuf::from(home).join(HISTORY_FILE));
+        if let Some(path) = &history_path {
+            // A missing history file just means this is the first session
+            let _ = editor.load_history(path);
+        }
+
+        Self { editor, history_path }
+    }
+
+    pub fn read_line(&mut self, prompt: &str) -> Input {
+        self.read_line_with(prompt, "")
+    }
+
+    /// Prompt with `initial` already typed, ready to be edited
+    pub fn read_line_with(&mut self, prompt: &str, initial: &str) -> Input {
+        match self.editor.readline_with_initial(prompt, (initial, "")) {
+            Ok(line) => Input::Line(line),
+            Err(ReadlineError::Interrupted) => Input::Interrupted,
+            Err(_) => Input::Eof,
+        }
+    }
+
+    pub fn add_history(&mut self, line: &str) {
+        let _ = self.editor.add_history_entry(line);
+    }
+
+    pub fn save_history(&mut self) {
+        if let Some(path) = &self.history_path {
+            if let Err(e) = self.editor.save_history(path) {
+                eprintln!("Warning: could not save history: {}", e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::complete_word;
+
+    #[test]
+    fn completes_the_word_before_the_cursor() {
+        assert_eq!(complete_word("10 pr", 5), (3, vec!["PRINT".to_string()]));
+        assert_eq!(complete_word("GO", 2), (0, vec!["GOTO".to_string()]));
+        assert_eq!(complete_word("10 ", 3), (3, Vec::new()));
+    }
+
+    #[test]
+    fn words_after_multibyte_characters() {
+        assert_eq!(complete_word("PRINT \"é", 9), (9, Vec::new()));
+        assert_eq!(complete_word("PRINT \"é\"; ", 12), (12, Vec::new()));
+        assert_eq!(complete_word("PRINT \"éG", 10), (9, vec!["GOTO".to_string()]));
+        assert_eq!(complete_word("éi", 3), (2, vec!["INPUT".to_string(), "IF".to_string()]));
+    }
+}
@@ -1,23 +1,48 @@
 /// Interpreter for Tiny BASIC - executes parsed programs
 use crate::ast::{BinaryOp, Expr, Line, PrintItem, Stmt};
-use std::collections::HashMap;
+use crate::bytecode::{self, Code, Op};
+use crate::cancel::CancelToken;
+use crate::console::{self, Input, Output};
+use crate::limits::Limits;
+use crate::native::{Registry, Value, ValueType};
+use crate::printer::format_stmt;
+use crate::program::Program;
+use std::collections::{HashMap, HashSet};
 use std::fmt;
+use std::io;
+use std::time::{Duration, Instant};
 
 #[derive(Debug)]
-#[allow(dead_code)]
+#[non_exhaustive]
 pub enum RuntimeError {
     DivisionByZero,
+    /// An arithmetic result doesn't fit in a 32-bit integer
+    Overflow,
     UndefinedVariable(char),
     UndefinedArray(char),
     ArrayNotDimensioned(char),
     InvalidLineNumber(i32),
     IndexOutOfBounds { array: char, index: i32, size: i32 },
+    NoSuchArgument { index: i32, count: i32 },
+    InvalidInput(String),
+    EndOfInput,
+    Io(io::Error),
+    UndefinedFunction(String),
+    WrongArgumentCount { function: String, expected: usize, got: usize },
+    TypeMismatch { expected: ValueType, got: ValueType },
+    NativeError { function: String, message: String },
+    StatementLimitExceeded(u64),
+    TimeLimitExceeded(Duration),
+    ArrayMemoryExceeded { requested: usize, limit: usize },
+    OutOfMemory { array: char, size: i32 },
+    StringTooLong { len: usize, limit: usize },
 }
 
 impl fmt::Display for RuntimeError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             RuntimeError::DivisionByZero => write!(f, "Division by zero"),
+            RuntimeError::Overflow => write!(f, "Arithmetic overflow"),
             RuntimeError::UndefinedVariable(c) => write!(f, "Undefined variable: {}", c),
             RuntimeError::UndefinedArray(c) => write!(f, "Undefined array: {}", c),
             RuntimeError::ArrayNotDimensioned(c) => write!(f, "Array {} not dimensioned", c),
@@ -25,26 +50,187 @@ impl fmt::Display for RuntimeError {
             RuntimeError::IndexOutOfBounds { array, index, size } => {
                 write!(f, "Index {} out of bounds for array {} (size {})", index, array, size)
             }
+            RuntimeError::NoSuchArgument { index, count } => {
+                write!(f, "No argument {} (program was given {})", index, count)
+            }
+            RuntimeError::InvalidInput(s) => write!(f, "Invalid input: {}", s),
+            RuntimeError::EndOfInput => write!(f, "Out of input"),
+            RuntimeError::Io(e) => write!(f, "I/O error: {}", e),
+            RuntimeError::UndefinedFunction(name) => write!(f, "Undefined function: {}", name),
+            RuntimeError::WrongArgumentCount { function, expected, got } => {
+                write!(f, "{} expects {} argument(s), got {}", function, expected, got)
+            }
+            RuntimeError::TypeMismatch { expected, got } => {
+                write!(f, "Type mismatch: expected {}, got {}", expected, got)
+            }
+            RuntimeError::NativeError { function, message } => write!(f, "{}: {}", function, message),
+            RuntimeError::StatementLimitExceeded(n) => write!(f, "Statement limit of {} exceeded", n),
+            RuntimeError::TimeLimitExceeded(d) => write!(f, "Time limit of {:?} exceeded", d),
+            RuntimeError::ArrayMemoryExceeded { requested, limit } => {
+                write!(f, "Arrays would use {} bytes, limit is {}", requested, limit)
+            }
+            RuntimeError::OutOfMemory { array, size } => {
+                write!(f, "Not enough memory to DIM {}({})", array, size)
+            }
+            RuntimeError::StringTooLong { len, limit } => {
+                write!(f, "String of {} bytes exceeds limit of {}", len, limit)
+            }
+        }
+    }
+}
+
+/// A runtime error raised while a program runs, with the line that raised it
+#[derive(Debug)]
+pub struct RunError {
+    pub error: RuntimeError,
+    /// The failing line; `None` when a GOTO typed at the prompt names a
+    /// missing line, so no program line was involved
+    pub line: Option<i32>,
+    /// Source text of the failing statement, as LIST shows it
+    pub statement: Option<String>,
+}
+
+impl fmt::Display for RunError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.error)?;
+        if let Some(line) = self.line {
+            write!(f, " in line {}", line)?;
+        }
+        if let Some(statement) = &self.statement {
+            write!(f, ": {}", statement)?;
         }
+        Ok(())
+    }
+}
+
+/// A jump to a line that doesn't exist, found before the program runs
+#[derive(Debug)]
+pub struct LinkError {
+    pub line: i32,
+    pub target: i32,
+}
+
+impl fmt::Display for LinkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Undefined line {} in line {}", self.target, self.line)
     }
 }
 
+/// Find every GOTO/IF target that doesn't name a line of the program
+pub fn check_targets<'a>(lines: impl IntoIterator<Item = &'a Line> + Clone) -> Vec<LinkError> {
+    let numbers: HashSet<i32> = lines.clone().into_iter().map(|l| l.number).collect();
+    lines
+        .into_iter()
+        .flat_map(|l| {
+            l.stmt
+                .line_refs()
+                .into_iter()
+                .filter(|target| !numbers.contains(target))
+                .map(|target| LinkError { line: l.number, target })
+        })
+        .collect()
+}
+
+/// How a call to `run` or `cont` handed control back to the caller
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub enum RunStatus {
+    /// The program reached END or ran off its last line
+    Ended,
+    /// A STOP statement on the given line suspended execution
+    Stopped(i32),
+    /// The cancel token was triggered before the given line executed
+    Interrupted(i32),
+    /// An INPUT on the given line has no input available yet
+    WaitingForInput(i32),
+}
+
+impl fmt::Display for RunStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RunStatus::Ended => write!(f, "Ended"),
+            RunStatus::Stopped(n) => write!(f, "Stopped at line {}", n),
+            RunStatus::Interrupted(n) => write!(f, "Break in {}", n),
+            RunStatus::WaitingForInput(n) => write!(f, "Waiting for input in line {}", n),
+        }
+    }
+}
+
+/// Outcome of executing a single statement with `step`
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum StepStatus {
+    /// More statements remain; call `step` again
+    Continue,
+    /// The program reached END or ran off its last line
+    Ended,
+    /// A STOP statement on the given line suspended execution
+    Stopped(i32),
+    /// An INPUT on the given line found no input available (its source
+    /// returned `WouldBlock`); `step` again once the host has some
+    WaitingForInput(i32),
+    /// The statement failed; the program can't continue
+    Error(RunError),
+}
+
+/// How `run` and `cont` execute the program. Both give the same results;
+/// `step` always uses the tree-walker.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[non_exhaustive]
+pub enum Engine {
+    /// Evaluate the AST directly
+    #[default]
+    TreeWalker,
+    /// Compile the program to bytecode once and run it on a stack VM
+    Bytecode,
+}
+
+/// Callback run around each program line, e.g. by a tracer or debugger
+pub type LineHook = Box<dyn FnMut(&Interpreter, &Line) + Send>;
+
 pub struct Interpreter {
     variables: HashMap<char, i32>,
     arrays: HashMap<char, Vec<i32>>,
-    program: Vec<Line>,
+    program: Program,
+    args: Vec<i32>,
     line_index: usize,
     done: bool,
+    cancel: CancelToken,
+    output: Box<dyn Output>,
+    input: Box<dyn Input>,
+    functions: Registry,
+    before_line: Option<LineHook>,
+    after_line: Option<LineHook>,
+    waiting_for_input: bool,
+    limits: Limits,
+    statements_executed: u64,
+    started: Option<Instant>,
+    engine: Engine,
+    /// The program compiled for `Engine::Bytecode`, built on first use
+    code: Option<Code>,
 }
 
 impl Interpreter {
-    pub fn new(program: Vec<Line>) -> Self {
+    pub fn new(program: Program) -> Self {
         let mut interp = Self {
             variables: HashMap::new(),
             arrays: HashMap::new(),
             program,
+            args: Vec::new(),
             line_index: 0,
             done: false,
+            cancel: CancelToken::new(),
+            output: Box::new(console::Stdout),
+            input: Box::new(console::Stdin),
+            functions: Registry::default(),
+            before_line: None,
+            after_line: None,
+            waiting_for_input: false,
+            limits: Limits::default(),
+            statements_executed: 0,
+            started: None,
+            engine: Engine::default(),
+            code: None,
         };
         // Initialize all variables A-Z to 0
         for c in 'A'..='Z' {
@@ -53,15 +239,132 @@ impl Interpreter {
         interp
     }
 
+    /// Send PRINT output somewhere other than stdout
+    pub fn set_output(&mut self, output: impl Output + 'static) {
+        self.output = Box::new(output);
+    }
+
+    /// Read INPUT values from somewhere other than stdin
+    pub fn set_input(&mut self, input: impl Input + 'static) {
+        self.input = Box::new(input);
+    }
+
+    /// Make a Rust closure callable from BASIC as `name(args...)`. Arguments
+    /// are checked against `params` before `func` runs, and an `Err` it
+    /// returns surfaces as `RuntimeError::NativeError`.
+    ///
+    /// # Panics
+    ///
+    /// If `name` is invalid; see `Registry::register`.
+    pub fn register_function<F>(&mut self, name: &str, params: &[ValueType], returns: ValueType, func: F)
+    where
+        F: Fn(&[Value]) -> Result<Value, String> + Send + 'static,
+    {
+        self.functions.register(name, params, returns, Box::new(func));
+    }
+
+    /// Bound what the program may consume; see `Limits`
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.limits = limits;
+    }
+
+    /// Choose how `run` and `cont` execute the program
+    pub fn set_engine(&mut self, engine: Engine) {
+        self.engine = engine;
+    }
+
+    /// A token that stops `run`/`cont` before the next statement when
+    /// cancelled, e.g. from another thread. It is cleared when honoured.
+    pub fn cancel_token(&self) -> CancelToken {
+        self.cancel.clone()
+    }
+
+    /// Use an existing token, e.g. one shared with a Ctrl-C handler
+    pub fn set_cancel_token(&mut self, token: CancelToken) {
+        self.cancel = token;
+    }
+
+    /// Check the program once before it runs so that a bad jump target is
+    /// reported up front rather than when execution reaches it
+    pub fn link(&self) -> Result<(), Vec<LinkError>> {
+        let errors = check_targets(self.program.lines());
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
     fn get_line_index(&self, line_num: i32) -> Result<usize, RuntimeError> {
         self.program
-            .iter()
-            .position(|l| l.number == line_num)
+            .index_of(line_num)
             .ok_or(RuntimeError::InvalidLineNumber(line_num))
     }
 
+    /// Evaluate an expression that may produce a string (a string literal or
+    /// a native `$` function); everything else is an integer
+    fn eval_value(&self, expr: &Expr) -> Result<Value, RuntimeError> {
+        match expr {
+            Expr::Str(s) => Ok(Value::Str(s.clone())),
+            Expr::Call(name, args) => self.call_function(name, args),
+            _ => Ok(Value::Int(self.eval_expr(expr)?)),
+        }
+    }
+
+    fn call_function(&self, name: &str, args: &[Expr]) -> Result<Value, RuntimeError> {
+        let function = self
+            .functions
+            .get(name)
+            .ok_or_else(|| RuntimeError::UndefinedFunction(name.to_string()))?;
+        if args.len() != function.params.len() {
+            return Err(RuntimeError::WrongArgumentCount {
+                function: name.to_string(),
+                expected: function.params.len(),
+                got: args.len(),
+            });
+        }
+        let mut values = Vec::with_capacity(args.len());
+        for (arg, &expected) in args.iter().zip(&function.params) {
+            let value = self.eval_value(arg)?;
+            if value.value_type() != expected {
+                return Err(RuntimeError::TypeMismatch {
+                    expected,
+                    got: value.value_type(),
+                });
+            }
+            values.push(value);
+        }
+        let result = (function.func)(&values).map_err(|message| RuntimeError::NativeError {
+            function: name.to_string(),
+            message,
+        })?;
+        if result.value_type() != function.returns {
+            return Err(RuntimeError::TypeMismatch {
+                expected: function.returns,
+                got: result.value_type(),
+            });
+        }
+        if let (Value::Str(s), Some(limit)) = (&result, self.limits.max_string_len) {
+            if s.len() > limit {
+                return Err(RuntimeError::StringTooLong { len: s.len(), limit });
+            }
+        }
+        Ok(result)
+    }
+
     fn eval_expr(&self, expr: &Expr) -> Result<i32, RuntimeError> {
         match expr {
+            Expr::Str(_) => Err(RuntimeError::TypeMismatch {
+                expected: ValueType::Int,
+                got: ValueType::Str,
+            }),
+            Expr::Call(name, args) => match self.call_function(name, args)? {
+                Value::Int(n) => Ok(n),
+                Value::Str(_) => Err(RuntimeError::TypeMismatch {
+                    expected: ValueType::Int,
+                    got: ValueType::Str,
+                }),
+            },
             Expr::Number(n) => Ok(*n),
             Expr::Variable(c) => self
                 .variables
@@ -70,44 +373,27 @@ impl Interpreter {
                 .ok_or(RuntimeError::UndefinedVariable(*c)),
             Expr::ArrayAccess(name, index_expr) => {
                 let index = self.eval_expr(index_expr)?;
-                let arr = self
-                    .arrays
-                    .get(name)
-                    .ok_or(RuntimeError::ArrayNotDimensioned(*name))?;
-                if index < 0 || index >= arr.len() as i32 {
-                    return Err(RuntimeError::IndexOutOfBounds {
-                        array: *name,
-                        index,
-                        size: arr.len() as i32,
-                    });
-                }
-                Ok(arr[index as usize])
+                self.array_element(*name, index)
             }
+            Expr::Neg(operand) => self.eval_expr(operand)?.checked_neg().ok_or(RuntimeError::Overflow),
+            Expr::Argc => Ok(self.args.len() as i32),
+            Expr::Argv(index_expr) => self.argv(self.eval_expr(index_expr)?),
             Expr::Binary { left, op, right } => {
                 let l = self.eval_expr(left)?;
                 let r = self.eval_expr(right)?;
-                match op {
-                    BinaryOp::Add => Ok(l + r),
-                    BinaryOp::Sub => Ok(l - r),
-                    BinaryOp::Mul => Ok(l * r),
-                    BinaryOp::Div => {
-                        if r == 0 {
-                            Err(RuntimeError::DivisionByZero)
-                        } else {
-                            Ok(l / r)
-                        }
-                    }
-                    BinaryOp::Eq => Ok((l == r) as i32),
-                    BinaryOp::Ne => Ok((l != r) as i32),
-                    BinaryOp::Lt => Ok((l < r) as i32),
-                    BinaryOp::Le => Ok((l <= r) as i32),
-                    BinaryOp::Gt => Ok((l > r) as i32),
-                    BinaryOp::Ge => Ok((l >= r) as i32),
-                }
+                binary(*op, l, r)
             }
         }
     }
 
+    fn argv(&self, index: i32) -> Result<i32, RuntimeError> {
+        let count = self.args.len() as i32;
+        if index < 1 || index > count {
+            return Err(RuntimeError::NoSuchArgument { index, count });
+        }
+        Ok(self.args[index as usize - 1])
+    }
+
     fn execute_statement(&mut self, stmt: &Stmt) -> Result<Option<i32>, RuntimeError> {
         match stmt {
             Stmt::Print(items) => {
@@ -115,10 +401,34 @@ impl Interpreter {
                 for item in items {
                     match item {
                         PrintItem::String(s) => output.push(s.clone()),
-                        PrintItem::Expr(expr) => output.push(self.eval_expr(expr)?.to_string()),
+                        PrintItem::Expr(expr) => output.push(self.eval_value(expr)?.to_string()),
                     }
                 }
-                println!("{}", output.join(" "));
+                self.output.write_line(&output.join(" ")).map_err(RuntimeError::Io)?;
+                Ok(None)
+            }
+            Stmt::Input { prompt, vars } => {
+                let prompt = format!("{}? ", prompt.as_deref().unwrap_or(""));
+                let line = self
+                    .input
+                    .read_line(&prompt)
+                    .map_err(RuntimeError::Io)?
+                    .ok_or(RuntimeError::EndOfInput)?;
+                let values: Vec<&str> = line.split(',').map(str::trim).collect();
+                if values.len() != vars.len() {
+                    return Err(RuntimeError::InvalidInput(format!(
+                        "expected {} value{}, got '{}'",
+                        vars.len(),
+                        if vars.len() == 1 { "" } else { "s" },
+                        line
+                    )));
+                }
+                for (var, value) in vars.iter().zip(values) {
+                    let n = value
+                        .parse::<i32>()
+                        .map_err(|_| RuntimeError::InvalidInput(format!("'{}' is not a number", value)))?;
+                    self.variables.insert(*var, n);
+                }
                 Ok(None)
             }
             Stmt::Let(var, value) => {
@@ -129,18 +439,7 @@ impl Interpreter {
             Stmt::LetArray(name, index_expr, value) => {
                 let index = self.eval_expr(index_expr)?;
                 let val = self.eval_expr(value)?;
-                let arr = self
-                    .arrays
-                    .get_mut(name)
-                    .ok_or(RuntimeError::ArrayNotDimensioned(*name))?;
-                if index < 0 || index >= arr.len() as i32 {
-                    return Err(RuntimeError::IndexOutOfBounds {
-                        array: *name,
-                        index,
-                        size: arr.len() as i32,
-                    });
-                }
-                arr[index as usize] = val;
+                self.set_array_element(*name, index, val)?;
                 Ok(None)
             }
             Stmt::Goto(line_num) => Ok(Some(*line_num)),
@@ -156,38 +455,413 @@ impl Interpreter {
                 self.done = true;
                 Ok(None)
             }
+            // Suspending is handled by `step`, which knows the line number
+            Stmt::Stop => Ok(None),
             Stmt::Dim(name, size) => {
-                if *size < 0 {
-                    return Err(RuntimeError::IndexOutOfBounds {
-                        array: *name,
-                        index: *size,
-                        size: 0,
-                    });
-                }
-                self.arrays
-                    .insert(*name, vec![0; *size as usize]);
+                self.dim_array(*name, *size)?;
                 Ok(None)
             }
         }
     }
 
-    pub fn run(&mut self) -> Result<(), RuntimeError> {
-        if self.program.is_empty() {
-            return Ok(());
+    /// Value of a variable A-Z (case-insensitive)
+    pub fn variable(&self, name: char) -> Option<i32> {
+        self.variables.get(&name.to_ascii_uppercase()).copied()
+    }
+
+    pub fn set_variable(&mut self, name: char, value: i32) -> Result<(), RuntimeError> {
+        let name = name.to_ascii_uppercase();
+        let slot = self
+            .variables
+            .get_mut(&name)
+            .ok_or(RuntimeError::UndefinedVariable(name))?;
+        *slot = value;
+        Ok(())
+    }
+
+    /// Contents of an array, or `None` if it hasn't been dimensioned
+    pub fn array(&self, name: char) -> Option<&[i32]> {
+        self.arrays.get(&name.to_ascii_uppercase()).map(Vec::as_slice)
+    }
+
+    pub fn array_element(&self, name: char, index: i32) -> Result<i32, RuntimeError> {
+        let name = name.to_ascii_uppercase();
+        let arr = self.arrays.get(&name).ok_or(RuntimeError::ArrayNotDimensioned(name))?;
+        if index < 0 || index >= arr.len() as i32 {
+            return Err(RuntimeError::IndexOutOfBounds {
+                array: name,
+                index,
+                size: arr.len() as i32,
+            });
+        }
+        Ok(arr[index as usize])
+    }
+
+    pub fn set_array_element(&mut self, name: char, index: i32, value: i32) -> Result<(), RuntimeError> {
+        let name = name.to_ascii_uppercase();
+        let arr = self
+            .arrays
+            .get_mut(&name)
+            .ok_or(RuntimeError::ArrayNotDimensioned(name))?;
+        if index < 0 || index >= arr.len() as i32 {
+            return Err(RuntimeError::IndexOutOfBounds {
+                array: name,
+                index,
+                size: arr.len() as i32,
+            });
         }
+        arr[index as usize] = value;
+        Ok(())
+    }
+
+    /// Create (or re-create) an array of `size` zeroes, as DIM does
+    pub fn dim_array(&mut self, name: char, size: i32) -> Result<(), RuntimeError> {
+        let name = name.to_ascii_uppercase();
+        if !name.is_ascii_uppercase() {
+            return Err(RuntimeError::UndefinedArray(name));
+        }
+        if size < 0 {
+            return Err(RuntimeError::IndexOutOfBounds {
+                array: name,
+                index: size,
+                size: 0,
+            });
+        }
+
+        let element = std::mem::size_of::<i32>();
+        if let Some(limit) = self.limits.max_array_bytes {
+            let others: usize = self
+                .arrays
+                .iter()
+                .filter(|(&n, _)| n != name)
+                .map(|(_, arr)| arr.len() * element)
+                .sum();
+            let requested = others.saturating_add((size as usize).saturating_mul(element));
+            if requested > limit {
+                return Err(RuntimeError::ArrayMemoryExceeded { requested, limit });
+            }
+        }
+
+        // Report a failed allocation instead of aborting the process
+        let mut arr = Vec::new();
+        arr.try_reserve_exact(size as usize)
+            .map_err(|_| RuntimeError::OutOfMemory { array: name, size })?;
+        arr.resize(size as usize, 0);
+        self.arrays.insert(name, arr);
+        Ok(())
+    }
+
+    /// Set the command-line arguments the program reads with ARGC and ARGV
+    pub fn set_args(&mut self, args: Vec<i32>) {
+        self.args = args;
+    }
+
+    /// Replace the program while keeping variables and arrays
+    pub fn set_program(&mut self, program: Program) {
+        self.program = program;
+        self.code = None;
+        self.line_index = 0;
+        self.waiting_for_input = false;
+    }
+
+    /// Execute a statement typed without a line number. Returns the target
+    /// line if the statement jumps into the program.
+    pub fn execute_immediate(&mut self, stmt: &Stmt) -> Result<Option<i32>, RuntimeError> {
+        self.execute_statement(stmt)
+    }
 
+    /// Start executing at the given line without resetting any state
+    pub fn goto(&mut self, line_num: i32) -> Result<RunStatus, RunError> {
+        self.line_index = self.get_line_index(line_num).map_err(|error| RunError {
+            error,
+            line: None,
+            statement: None,
+        })?;
+        self.restart();
+        self.cont()
+    }
+
+    pub fn run(&mut self) -> Result<RunStatus, RunError> {
         self.line_index = 0;
+        self.restart();
+        self.cont()
+    }
+
+    /// Forget how the last run ended before starting a new one. An INPUT it
+    /// was waiting on is abandoned, so the first statement is charged and
+    /// hooked like any other.
+    fn restart(&mut self) {
         self.done = false;
+        self.waiting_for_input = false;
+        self.statements_executed = 0;
+        self.started = None;
+    }
 
-        while !self.done && self.line_index < self.program.len() {
-            let stmt = self.program[self.line_index].stmt.clone();
-            if let Some(goto_line) = self.execute_statement(&stmt)? {
-                self.line_index = self.get_line_index(goto_line)?;
-            } else {
-                self.line_index += 1;
+    /// Resume execution at the saved line index, e.g. after STOP or a break
+    pub fn cont(&mut self) -> Result<RunStatus, RunError> {
+        match self.engine {
+            Engine::TreeWalker => self.cont_tree(),
+            Engine::Bytecode => {
+                let code = match self.code.take() {
+                    Some(code) => code,
+                    None => bytecode::compile(self.program.lines()),
+                };
+                let result = self.cont_bytecode(&code);
+                self.code = Some(code);
+                result
             }
         }
+    }
+
+    fn cont_tree(&mut self) -> Result<RunStatus, RunError> {
+        loop {
+            if let Some(line) = self.program.lines().get(self.line_index) {
+                if !self.done && self.cancel.take() {
+                    return Ok(RunStatus::Interrupted(line.number));
+                }
+            }
+            match self.step() {
+                StepStatus::Continue => {}
+                StepStatus::Ended => return Ok(RunStatus::Ended),
+                StepStatus::Stopped(n) => return Ok(RunStatus::Stopped(n)),
+                StepStatus::WaitingForInput(n) => return Ok(RunStatus::WaitingForInput(n)),
+                StepStatus::Error(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Line number of the statement the next `step` will execute
+    pub fn current_line(&self) -> Option<i32> {
+        match self.program.lines().get(self.line_index) {
+            Some(line) if !self.done => Some(line.number),
+            _ => None,
+        }
+    }
+
+    /// Call `hook` just before each program line executes
+    pub fn set_before_line_hook(&mut self, hook: impl FnMut(&Interpreter, &Line) + Send + 'static) {
+        self.before_line = Some(Box::new(hook));
+    }
 
+    /// Call `hook` after each program line executes without error
+    pub fn set_after_line_hook(&mut self, hook: impl FnMut(&Interpreter, &Line) + Send + 'static) {
+        self.after_line = Some(Box::new(hook));
+    }
+
+    /// Count one statement against the statement and time limits
+    fn charge_statement(&mut self) -> Result<(), RuntimeError> {
+        if let Some(max) = self.limits.max_statements {
+            if self.statements_executed >= max {
+                return Err(RuntimeError::StatementLimitExceeded(max));
+            }
+        }
+        self.statements_executed += 1;
+
+        if let Some(timeout) = self.limits.timeout {
+            let started = *self.started.get_or_insert_with(Instant::now);
+            if started.elapsed() > timeout {
+                return Err(RuntimeError::TimeLimitExceeded(timeout));
+            }
+        }
         Ok(())
     }
+
+    /// Attach the line at `index` to an error raised while executing it
+    fn located(&self, error: RuntimeError, index: usize) -> RunError {
+        let line = &self.program.lines()[index];
+        RunError {
+            error,
+            line: Some(line.number),
+            statement: Some(format_stmt(&line.stmt)),
+        }
+    }
+
+    fn call_hook(&mut self, which: fn(&mut Self) -> &mut Option<LineHook>, index: usize) {
+        if let Some(mut hook) = which(self).take() {
+            hook(self, &self.program.lines()[index]);
+            *which(self) = Some(hook);
+        }
+    }
+
+    /// Execute the statement at the current line and advance past it. This
+    /// lets a host interleave the program with its own event loop.
+    pub fn step(&mut self) -> StepStatus {
+        let index = self.line_index;
+        let line = match self.program.lines().get(index) {
+            Some(line) if !self.done => line,
+            _ => return StepStatus::Ended,
+        };
+        let line_num = line.number;
+        let is_stop = matches!(line.stmt, Stmt::Stop);
+
+        // An INPUT that is still waiting has already been announced and counted
+        if !self.waiting_for_input {
+            if let Err(e) = self.charge_statement() {
+                return StepStatus::Error(self.located(e, index));
+            }
+            self.call_hook(|i| &mut i.before_line, index);
+        }
+        self.waiting_for_input = false;
+
+        // Hold the lines through a handle of our own so the statement can run
+        // by reference while the interpreter is borrowed mutably
+        let lines = self.program.shared_lines();
+        let result = self.execute_statement(&lines[index].stmt);
+        let jump = match result {
+            Ok(jump) => jump,
+            Err(RuntimeError::Io(e)) if e.kind() == io::ErrorKind::WouldBlock => {
+                self.waiting_for_input = true;
+                return StepStatus::WaitingForInput(line_num);
+            }
+            Err(e) => return StepStatus::Error(self.located(e, index)),
+        };
+        match jump {
+            Some(goto_line) => match self.get_line_index(goto_line) {
+                Ok(target) => self.line_index = target,
+                Err(e) => return StepStatus::Error(self.located(e, index)),
+            },
+            None => self.line_index += 1,
+        }
+        self.call_hook(|i| &mut i.after_line, index);
+
+        if is_stop {
+            StepStatus::Stopped(line_num)
+        } else if self.done || self.line_index >= self.program.len() {
+            StepStatus::Ended
+        } else {
+            StepStatus::Continue
+        }
+    }
+
+    /// The bytecode VM. It keeps `line_index` pointing at the line being
+    /// executed, so it stops and resumes at the same places as the
+    /// tree-walker and the two can take turns on one program.
+    fn cont_bytecode(&mut self, code: &Code) -> Result<RunStatus, RunError> {
+        if self.done || self.line_index >= self.program.len() {
+            return Ok(RunStatus::Ended);
+        }
+        let mut stack: Vec<i32> = Vec::new();
+        let mut pc = code.line_starts[self.line_index];
+        // Line whose statement is running, for the after-line hook
+        let mut current: Option<usize> = None;
+
+        macro_rules! pop {
+            () => {
+                stack.pop().expect("bytecode stack underflow")
+            };
+        }
+        macro_rules! check {
+            ($result:expr) => {
+                match $result {
+                    Ok(value) => value,
+                    Err(e) => return Err(self.located(e, self.line_index)),
+                }
+            };
+        }
+
+        loop {
+            let op = &code.ops[pc];
+            pc += 1;
+            match *op {
+                Op::Line(index) => {
+                    self.line_index = index;
+                    if let Some(previous) = current.take() {
+                        self.call_hook(|i| &mut i.after_line, previous);
+                    }
+                    if self.cancel.take() {
+                        return Ok(RunStatus::Interrupted(self.program.lines()[index].number));
+                    }
+                    // An INPUT that is still waiting has already been announced and counted
+                    if !self.waiting_for_input {
+                        check!(self.charge_statement());
+                        self.call_hook(|i| &mut i.before_line, index);
+                    }
+                    self.waiting_for_input = false;
+                    current = Some(index);
+                }
+                Op::Push(n) => stack.push(n),
+                Op::Load(name) => {
+                    let value = check!(self.variables.get(&name).copied().ok_or(RuntimeError::UndefinedVariable(name)));
+                    stack.push(value);
+                }
+                Op::LoadElement(name) => {
+                    let index = pop!();
+                    stack.push(check!(self.array_element(name, index)));
+                }
+                Op::Store(name) => {
+                    let value = pop!();
+                    self.variables.insert(name, value);
+                }
+                Op::StoreElement(name) => {
+                    let value = pop!();
+                    let index = pop!();
+                    check!(self.set_array_element(name, index, value));
+                }
+                Op::Argc => stack.push(self.args.len() as i32),
+                Op::Argv => {
+                    let index = pop!();
+                    stack.push(check!(self.argv(index)));
+                }
+                Op::Neg => {
+                    let value = pop!();
+                    stack.push(check!(value.checked_neg().ok_or(RuntimeError::Overflow)));
+                }
+                Op::Binary(op) => {
+                    let r = pop!();
+                    let l = pop!();
+                    stack.push(check!(binary(op, l, r)));
+                }
+                Op::Eval(index) => stack.push(check!(self.eval_expr(&code.exprs[index]))),
+                Op::Exec(index) => match self.execute_statement(&code.stmts[index]) {
+                    Ok(_) => {}
+                    Err(RuntimeError::Io(e)) if e.kind() == io::ErrorKind::WouldBlock => {
+                        self.waiting_for_input = true;
+                        return Ok(RunStatus::WaitingForInput(self.program.lines()[self.line_index].number));
+                    }
+                    Err(e) => return Err(self.located(e, self.line_index)),
+                },
+                Op::Jump(to) => pc = to,
+                Op::JumpIf(to) => {
+                    if pop!() != 0 {
+                        pc = to;
+                    }
+                }
+                Op::Fail(target) => check!(Err(RuntimeError::InvalidLineNumber(target))),
+                Op::Stop | Op::End | Op::Halt => {
+                    let index = self.line_index;
+                    let number = self.program.lines()[index].number;
+                    self.done = matches!(op, Op::End);
+                    self.line_index += 1;
+                    self.call_hook(|i| &mut i.after_line, index);
+                    return Ok(match op {
+                        Op::Stop => RunStatus::Stopped(number),
+                        _ => RunStatus::Ended,
+                    });
+                }
+            }
+        }
+    }
+}
+
+/// Apply an arithmetic or comparison operator; comparisons give 1 or 0
+fn binary(op: BinaryOp, l: i32, r: i32) -> Result<i32, RuntimeError> {
+    match op {
+        BinaryOp::Add => l.checked_add(r).ok_or(RuntimeError::Overflow),
+        BinaryOp::Sub => l.checked_sub(r).ok_or(RuntimeError::Overflow),
+        BinaryOp::Mul => l.checked_mul(r).ok_or(RuntimeError::Overflow),
+        BinaryOp::Div => {
+            if r == 0 {
+                Err(RuntimeError::DivisionByZero)
+            } else {
+                // i32::MIN / -1 is the one quotient that doesn't fit
+                l.checked_div(r).ok_or(RuntimeError::Overflow)
+            }
+        }
+        BinaryOp::Eq => Ok((l == r) as i32),
+        BinaryOp::Ne => Ok((l != r) as i32),
+        BinaryOp::Lt => Ok((l < r) as i32),
+        BinaryOp::Le => Ok((l <= r) as i32),
+        BinaryOp::Gt => Ok((l > r) as i32),
+        BinaryOp::Ge => Ok((l >= r) as i32),
+    }
 }
@@ -1,4 +1,5 @@
 /// Lexer for Tiny BASIC - tokenizes source code
+use std::fmt;
 use std::iter::Peekable;
 use std::str::Chars;
 
@@ -6,15 +7,21 @@ use std::str::Chars;
 pub enum Token {
     Number(i32),
     Ident(char),
+    /// Multi-letter name that isn't a keyword, e.g. a native function
+    Name(String),
     String(String),
     // Keywords
     Print,
+    Input,
     Let,
     Goto,
     If,
     Then,
     End,
+    Stop,
     Dim,
+    Argc,
+    Argv,
     // Operators
     Plus,
     Minus,
@@ -30,41 +37,124 @@ pub enum Token {
     LeftParen,
     RightParen,
     Comma,
+    /// End of a source line; each program line is one statement
+    Newline,
+}
+
+impl Token {
+    /// How the token is named in error messages, e.g. `variable X`
+    pub fn describe(&self) -> String {
+        match self {
+            Token::Number(n) => format!("number {}", n),
+            Token::Ident(c) => format!("variable {}", c),
+            Token::Name(name) => name.clone(),
+            Token::String(s) => format!("string \"{}\"", s),
+            Token::Print => "PRINT".to_string(),
+            Token::Input => "INPUT".to_string(),
+            Token::Let => "LET".to_string(),
+            Token::Goto => "GOTO".to_string(),
+            Token::If => "IF".to_string(),
+            Token::Then => "THEN".to_string(),
+            Token::End => "END".to_string(),
+            Token::Stop => "STOP".to_string(),
+            Token::Dim => "DIM".to_string(),
+            Token::Argc => "ARGC".to_string(),
+            Token::Argv => "ARGV".to_string(),
+            Token::Plus => "'+'".to_string(),
+            Token::Minus => "'-'".to_string(),
+            Token::Star => "'*'".to_string(),
+            Token::Slash => "'/'".to_string(),
+            Token::Equals => "'='".to_string(),
+            Token::LessThan => "'<'".to_string(),
+            Token::GreaterThan => "'>'".to_string(),
+            Token::LessEq => "'<='".to_string(),
+            Token::GreaterEq => "'>='".to_string(),
+            Token::NotEquals => "'<>'".to_string(),
+            Token::LeftParen => "'('".to_string(),
+            Token::RightParen => "')'".to_string(),
+            Token::Comma => "','".to_string(),
+            Token::Newline => "end of line".to_string(),
+        }
+    }
+}
+
+/// Where a token or error sits in the source: 1-based line and column,
+/// and its length in characters
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Span {
+    pub line: usize,
+    pub column: usize,
+    pub len: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpannedToken {
+    pub token: Token,
+    pub span: Span,
 }
 
 #[derive(Debug)]
 pub struct LexerError {
     pub message: String,
-    pub position: usize,
+    pub span: Span,
 }
 
-impl std::fmt::Display for LexerError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{} at position {}", self.message, self.position)
+impl fmt::Display for LexerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
     }
 }
 
 pub struct Lexer<'a> {
     input: Peekable<Chars<'a>>,
-    position: usize,
+    line: usize,
+    column: usize,
 }
 
 impl<'a> Lexer<'a> {
     pub fn new(input: &'a str) -> Self {
-        Self {
+        let mut lexer = Self {
             input: input.chars().peekable(),
-            position: 0,
+            line: 1,
+            column: 1,
+        };
+        // Skip a `#!` line so programs can be run as executable scripts
+        if input.starts_with("#!") {
+            while let Some(c) = lexer.advance() {
+                if c == '\n' {
+                    break;
+                }
+            }
         }
+        lexer
     }
 
     fn advance(&mut self) -> Option<char> {
         let c = self.input.next();
-        if c.is_some() {
-            self.position += 1;
+        if c == Some('\n') {
+            self.line += 1;
+            self.column = 1;
+        } else if c.is_some() {
+            self.column += 1;
         }
         c
     }
 
+    /// Span from `start` to the current position, clipped to one line
+    fn span_from(&self, start: Span) -> Span {
+        let len = if self.line == start.line { self.column - start.column } else { 1 };
+        Span { len: len.max(1), ..start }
+    }
+
+    /// Where the input ends, for errors about something missing
+    pub fn end_span(&self) -> Span {
+        Span {
+            line: self.line,
+            column: self.column,
+            len: 1,
+        }
+    }
+
     fn peek(&mut self) -> Option<&char> {
         self.input.peek()
     }
@@ -79,18 +169,54 @@ impl<'a> Lexer<'a> {
         }
     }
 
-    pub fn tokenize(&mut self) -> Result<Vec<Token>, LexerError> {
-        let mut tokens = Vec::new();
+    /// Tokenize the whole input, stopping at the first error
+    pub fn tokenize(&mut self) -> Result<Vec<SpannedToken>, LexerError> {
+        let (tokens, mut errors) = self.tokenize_all();
+        if errors.is_empty() {
+            Ok(tokens)
+        } else {
+            Err(errors.swap_remove(0))
+        }
+    }
 
+    /// Tokenize the whole input, collecting every error. A line with an
+    /// error contributes no tokens at all, so the parser never sees half of
+    /// it, and lexing resumes on the next line.
+    pub fn tokenize_all(&mut self) -> (Vec<SpannedToken>, Vec<LexerError>) {
+        let mut tokens: Vec<SpannedToken> = Vec::new();
+        let mut errors = Vec::new();
+        while let Some(result) = self.next_token() {
+            match result {
+                Ok(token) => tokens.push(token),
+                Err(e) => {
+                    while tokens.last().is_some_and(|t| t.span.line == e.span.line) {
+                        tokens.pop();
+                    }
+                    while let Some(c) = self.advance() {
+                        if c == '\n' {
+                            break;
+                        }
+                    }
+                    errors.push(e);
+                }
+            }
+        }
+        (tokens, errors)
+    }
+
+    fn next_token(&mut self) -> Option<Result<SpannedToken, LexerError>> {
         loop {
             self.skip_whitespace();
-            let c = match self.advance() {
-                Some(ch) => ch,
-                None => break,
+            let start = Span {
+                line: self.line,
+                column: self.column,
+                len: 1,
             };
+            let c = self.advance()?;
 
             let token = match c {
-                '\n' | '\r' => continue,
+                '\n' => Token::Newline,
+                '\r' => continue,
                 '+' => Token::Plus,
                 '-' => Token::Minus,
                 '*' => Token::Star,
@@ -121,30 +247,46 @@ impl<'a> Lexer<'a> {
                 '"' => {
                     let mut s = String::new();
                     loop {
-                        match self.advance() {
-                            Some('"') => break,
-                            Some(ch) => s.push(ch),
-                            None => {
-                                return Err(LexerError {
+                        match self.peek() {
+                            Some('"') => {
+                                self.advance();
+                                break;
+                            }
+                            Some(&ch) if ch != '\n' => {
+                                self.advance();
+                                s.push(ch);
+                            }
+                            _ => {
+                                return Some(Err(LexerError {
                                     message: "Unterminated string".to_string(),
-                                    position: self.position,
-                                });
+                                    span: start,
+                                }));
                             }
                         }
                     }
                     Token::String(s)
                 }
                 '0'..='9' => {
-                    let mut num = c.to_digit(10).unwrap() as i32;
+                    let mut num = Some(c.to_digit(10).unwrap() as i32);
                     while let Some(&d) = self.peek() {
                         if d.is_ascii_digit() {
                             self.advance();
-                            num = num * 10 + d.to_digit(10).unwrap() as i32;
+                            num = num
+                                .and_then(|n| n.checked_mul(10))
+                                .and_then(|n| n.checked_add(d.to_digit(10).unwrap() as i32));
                         } else {
                             break;
                         }
                     }
-                    Token::Number(num)
+                    match num {
+                        Some(n) => Token::Number(n),
+                        None => {
+                            return Some(Err(LexerError {
+                                message: "Number too large".to_string(),
+                                span: self.span_from(start),
+                            }));
+                        }
+                    }
                 }
                 'A'..='Z' | 'a'..='z' => {
                     let letter = c.to_ascii_uppercase();
@@ -159,39 +301,40 @@ impl<'a> Lexer<'a> {
                             break;
                         }
                     }
+                    if let Some(&'$') = self.peek() {
+                        self.advance();
+                        keyword.push('$');
+                    }
                     match keyword.as_str() {
                         "PRINT" => Token::Print,
+                        "INPUT" => Token::Input,
                         "LET" => Token::Let,
                         "GOTO" => Token::Goto,
                         "IF" => Token::If,
                         "THEN" => Token::Then,
                         "END" => Token::End,
+                        "STOP" => Token::Stop,
                         "DIM" => Token::Dim,
-                        _ => {
-                            // Single letter variable
-                            if keyword.len() == 1 {
-                                Token::Ident(keyword.chars().next().unwrap())
-                            } else {
-                                return Err(LexerError {
-                                    message: format!("Invalid identifier: {}", keyword),
-                                    position: self.position,
-                                });
-                            }
-                        }
+                        "ARGC" => Token::Argc,
+                        "ARGV" => Token::Argv,
+                        // Single letter variable
+                        _ if keyword.len() == 1 => Token::Ident(letter),
+                        _ => Token::Name(keyword),
                     }
                 }
                 _ => {
-                    return Err(LexerError {
-                        message: format!("Unexpected character: {}", c),
-                        position: self.position,
-                    });
+                    return Some(Err(LexerError {
+                        message: format!("Unexpected character '{}'", c),
+                        span: start,
+                    }));
                 }
             };
 
-            tokens.push(token);
+            return Some(Ok(SpannedToken {
+                token,
+                span: self.span_from(start),
+            }));
         }
-
-        Ok(tokens)
     }
 }
 
@@ -0,0 +1,43 @@
+//! Tiny BASIC interpreter library
+//!
+//! Parse a program with [`Program::parse`], hand it to [`Interpreter::new`]
+//! and either [`Interpreter::run`] it to completion or drive it one
+//! statement at a time with [`Interpreter::step`]. Variables and arrays can
+//! be read and written between steps.
+//!
+//! ```
+//! use rust_basic::{Interpreter, Program, RunStatus};
+//!
+//! let program = Program::parse("10 LET X = A * 2\n20 END\n").unwrap();
+//! let mut interp = Interpreter::new(program);
+//! interp.set_variable('A', 21).unwrap();
+//! assert_eq!(interp.run().unwrap(), RunStatus::Ended);
+//! assert_eq!(interp.variable('X'), Some(42));
+//! ```
+
+pub mod ast;
+pub(crate) mod bytecode;
+pub(crate) mod cancel;
+pub mod console;
+pub(crate) mod diagnostic;
+pub(crate) mod interpreter;
+pub(crate) mod lexer;
+pub(crate) mod limits;
+pub(crate) mod lint;
+pub(crate) mod native;
+pub(crate) mod optimize;
+pub(crate) mod parser;
+pub(crate) mod printer;
+pub(crate) mod program;
+
+pub use cancel::CancelToken;
+pub use console::{Input, Output};
+pub use interpreter::{check_targets, Engine, Interpreter, LinkError, RunError, RunStatus, RuntimeError, StepStatus};
+pub use lexer::{LexerError, Span};
+pub use limits::Limits;
+pub use lint::{lint, lints_to_json, Lint, Warning};
+pub use native::{Value, ValueType};
+pub use optimize::optimize;
+pub use parser::{parse, parse_immediate, parse_recovering, BrokenLine, ParseError};
+pub use printer::format_line;
+pub use program::Program;
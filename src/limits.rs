@@ -0,0 +1,17 @@
+/// Execution limits for Tiny BASIC - bounds for running untrusted programs
+use std::time::Duration;
+
+/// Resource limits checked while a program runs. `None` means unlimited,
+/// which is the default. Exceeding a limit stops the program with a
+/// dedicated `RuntimeError`.
+#[derive(Debug, Clone, Default)]
+pub struct Limits {
+    /// Statements executed per RUN (catches `10 GOTO 10`)
+    pub max_statements: Option<u64>,
+    /// Wall-clock time per RUN, measured from its first statement
+    pub timeout: Option<Duration>,
+    /// Total bytes held by all DIMensioned arrays
+    pub max_array_bytes: Option<usize>,
+    /// Length in bytes of strings returned by native functions
+    pub max_string_len: Option<usize>,
+}
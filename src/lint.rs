@@ -0,0 +1,301 @@
+/// Linter for Tiny BASIC - finds likely mistakes without running the program
+///
+/// `lint` walks the parsed program and returns warnings; none of them stop
+/// a program from running. Each warning has a stable code for tools, and
+/// `lints_to_json` renders a list of them for editors and CI.
+use crate::ast::{Expr, Stmt};
+use crate::interpreter::check_targets;
+use crate::optimize::fold;
+use crate::program::Program;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum Warning {
+    /// A GOTO or IF ... THEN names a line that doesn't exist
+    UndefinedTarget(i32),
+    /// No path from the first line reaches this line or the ones up to
+    /// `last`
+    Unreachable { last: i32 },
+    /// An array is used on a line before the first DIM of it
+    ArrayBeforeDim(char),
+    /// An array is used but never DIMensioned
+    ArrayNeverDimensioned(char),
+    /// A constant index outside every size the array is DIMensioned with
+    IndexOutOfRange { array: char, index: i32, size: i32 },
+    /// A variable is read but no LET or INPUT ever assigns it
+    NeverAssigned(char),
+    /// An IF condition that folds to the constant 0
+    ConditionAlwaysFalse,
+    /// No END statement anywhere in the program
+    MissingEnd,
+}
+
+impl Warning {
+    /// Short stable name of the check, e.g. for the JSON output
+    pub fn code(&self) -> &'static str {
+        match self {
+            Warning::UndefinedTarget(_) => "undefined-target",
+            Warning::Unreachable { .. } => "unreachable",
+            Warning::ArrayBeforeDim(_) => "array-before-dim",
+            Warning::ArrayNeverDimensioned(_) => "array-not-dimensioned",
+            Warning::IndexOutOfRange { .. } => "index-out-of-range",
+            Warning::NeverAssigned(_) => "never-assigned",
+            Warning::ConditionAlwaysFalse => "condition-always-false",
+            Warning::MissingEnd => "missing-end",
+        }
+    }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Warning::UndefinedTarget(target) => write!(f, "Jump to undefined line {}", target),
+            Warning::Unreachable { last } => write!(f, "Unreachable code (through line {})", last),
+            Warning::ArrayBeforeDim(c) => write!(f, "Array {} is used before it is DIMensioned", c),
+            Warning::ArrayNeverDimensioned(c) => write!(f, "Array {} is used but never DIMensioned", c),
+            Warning::IndexOutOfRange { array, index, size } => {
+                write!(f, "Index {} is out of range for array {} (size {})", index, array, size)
+            }
+            Warning::NeverAssigned(c) => write!(f, "Variable {} is read but never assigned", c),
+            Warning::ConditionAlwaysFalse => write!(f, "IF condition is always false"),
+            Warning::MissingEnd => write!(f, "Program has no END statement"),
+        }
+    }
+}
+
+/// A warning and the line it applies to; `None` for the whole program
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lint {
+    pub line: Option<i32>,
+    pub warning: Warning,
+}
+
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "Line {}: {} [{}]", line, self.warning, self.warning.code()),
+            None => write!(f, "{} [{}]", self.warning, self.warning.code()),
+        }
+    }
+}
+
+/// Check a program, returning warnings ordered by line
+pub fn lint(program: &Program) -> Vec<Lint> {
+    let mut lints: Vec<Lint> = check_targets(program.lines())
+        .into_iter()
+        .map(|e| Lint {
+            line: Some(e.line),
+            warning: Warning::UndefinedTarget(e.target),
+        })
+        .collect();
+    lints.extend(unreachable(program));
+    lints.extend(arrays_before_dim(program));
+    lints.extend(constant_indices_out_of_range(program));
+    lints.extend(never_assigned(program));
+    lints.extend(always_false(program));
+    lints.sort_by_key(|l| l.line.unwrap_or(i32::MAX));
+
+    if !program.lines().iter().any(|l| matches!(l.stmt, Stmt::End)) && !program.is_empty() {
+        lints.push(Lint {
+            line: None,
+            warning: Warning::MissingEnd,
+        });
+    }
+    lints
+}
+
+/// Lines no path from the first line reaches, grouped into runs
+fn unreachable(program: &Program) -> Vec<Lint> {
+    let lines = program.lines();
+    let mut reached = vec![false; lines.len()];
+    let mut pending = vec![0];
+    while let Some(i) = pending.pop() {
+        if i >= lines.len() || reached[i] {
+            continue;
+        }
+        reached[i] = true;
+        match &lines[i].stmt {
+            Stmt::Goto(target) => pending.extend(program.index_of(*target)),
+            Stmt::If { then_line, .. } => {
+                pending.extend(program.index_of(*then_line));
+                pending.push(i + 1);
+            }
+            Stmt::End => {}
+            _ => pending.push(i + 1),
+        }
+    }
+
+    let mut lints: Vec<Lint> = Vec::new();
+    for (i, line) in lines.iter().enumerate() {
+        if reached[i] {
+            continue;
+        }
+        match lints.last_mut() {
+            // Extend the run that ended on the previous line
+            Some(Lint {
+                warning: Warning::Unreachable { last },
+                ..
+            }) if i > 0 && *last == lines[i - 1].number => *last = line.number,
+            _ => lints.push(Lint {
+                line: Some(line.number),
+                warning: Warning::Unreachable { last: line.number },
+            }),
+        }
+    }
+    lints
+}
+
+/// The first use of each array that comes before its first DIM
+fn arrays_before_dim(program: &Program) -> Vec<Lint> {
+    let mut first_dim: HashMap<char, i32> = HashMap::new();
+    for line in program.lines() {
+        if let Stmt::Dim(name, _) = line.stmt {
+            first_dim.entry(name).or_insert(line.number);
+        }
+    }
+
+    let mut reported = HashSet::new();
+    let mut lints = Vec::new();
+    for line in program.lines() {
+        let mut used = BTreeSet::new();
+        if let Stmt::LetArray(name, ..) = line.stmt {
+            used.insert(name);
+        }
+        for expr in line.stmt.exprs() {
+            expr.visit(&mut |e| {
+                if let Expr::ArrayAccess(name, _) = e {
+                    used.insert(*name);
+                }
+            });
+        }
+        for name in used {
+            let warning = match first_dim.get(&name) {
+                Some(&dim) if dim < line.number => continue,
+                Some(_) => Warning::ArrayBeforeDim(name),
+                None => Warning::ArrayNeverDimensioned(name),
+            };
+            if reported.insert(name) {
+                lints.push(Lint {
+                    line: Some(line.number),
+                    warning,
+                });
+            }
+        }
+    }
+    lints
+}
+
+/// Array accesses whose index is a constant that no DIM of the array makes
+/// valid. Indices that depend on variables aren't checked.
+fn constant_indices_out_of_range(program: &Program) -> Vec<Lint> {
+    let mut sizes: HashMap<char, i32> = HashMap::new();
+    for line in program.lines() {
+        if let Stmt::Dim(name, size) = line.stmt {
+            let largest = sizes.entry(name).or_insert(size);
+            *largest = (*largest).max(size);
+        }
+    }
+
+    let mut lints = Vec::new();
+    for line in program.lines() {
+        // Each access as the array and its folded index
+        let mut accesses = Vec::new();
+        if let Stmt::LetArray(name, index, _) = &line.stmt {
+            accesses.push((*name, fold(*index.clone())));
+        }
+        for expr in line.stmt.exprs() {
+            expr.visit(&mut |e| {
+                if let Expr::ArrayAccess(name, index) = e {
+                    accesses.push((*name, fold(*index.clone())));
+                }
+            });
+        }
+        for (array, index) in accesses {
+            if let (Expr::Number(index), Some(&size)) = (index, sizes.get(&array)) {
+                if index < 0 || index >= size {
+                    lints.push(Lint {
+                        line: Some(line.number),
+                        warning: Warning::IndexOutOfRange { array, index, size },
+                    });
+                }
+            }
+        }
+    }
+    lints
+}
+
+/// Variables read somewhere but assigned nowhere; they are always 0
+fn never_assigned(program: &Program) -> Vec<Lint> {
+    let mut assigned = HashSet::new();
+    for line in program.lines() {
+        match &line.stmt {
+            Stmt::Let(name, _) => {
+                assigned.insert(*name);
+            }
+            Stmt::Input { vars, .. } => assigned.extend(vars.iter().copied()),
+            _ => {}
+        }
+    }
+
+    let mut reported = HashSet::new();
+    let mut lints = Vec::new();
+    for line in program.lines() {
+        for expr in line.stmt.exprs() {
+            expr.visit(&mut |e| {
+                if let Expr::Variable(name) = e {
+                    if !assigned.contains(name) && reported.insert(*name) {
+                        lints.push(Lint {
+                            line: Some(line.number),
+                            warning: Warning::NeverAssigned(*name),
+                        });
+                    }
+                }
+            });
+        }
+    }
+    lints
+}
+
+fn always_false(program: &Program) -> Vec<Lint> {
+    program
+        .lines()
+        .iter()
+        .filter(|l| matches!(&l.stmt, Stmt::If { condition, .. } if matches!(fold(*condition.clone()), Expr::Number(0))))
+        .map(|l| Lint {
+            line: Some(l.number),
+            warning: Warning::ConditionAlwaysFalse,
+        })
+        .collect()
+}
+
+/// Render lints as a JSON array of `{"line", "code", "message"}` objects;
+/// `line` is `null` for warnings about the whole program
+pub fn lints_to_json(lints: &[Lint]) -> String {
+    let items: Vec<String> = lints
+        .iter()
+        .map(|l| {
+            format!(
+                "{{\"line\": {}, \"code\": \"{}\", \"message\": \"{}\"}}",
+                l.line.map_or("null".to_string(), |n| n.to_string()),
+                l.warning.code(),
+                json_escape(&l.warning.to_string())
+            )
+        })
+        .collect();
+    format!("[{}]", items.join(", "))
+}
+
+fn json_escape(s: &str) -> String {
+    let mut out = String::new();
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out
+}
@@ -1,91 +1,310 @@
 //! Tiny BASIC Interpreter
 //!
 //! A minimal BASIC interpreter supporting:
-//! - PRINT, LET, GOTO, IF, END, DIM
+//! - PRINT, INPUT, LET, GOTO, IF, END, STOP, DIM
 //! - Variables A-Z, integer arithmetic
-//! - Commands: LOAD, SAVE, RUN, LIST, NEW, QUIT
+//! - Commands: LOAD, SAVE, RUN, CONT, LIST, EDIT, NEW, QUIT
+//!
+//! With arguments, or with a program piped to stdin, it runs
+//! non-interactively; see `cli.rs`.
 
-mod ast;
-mod interpreter;
-mod lexer;
-mod parser;
+mod cli;
+mod editor;
 
-use interpreter::Interpreter;
-use parser::parse;
+use editor::{Input, LineEditor};
+use rust_basic::{
+    ast, check_targets, format_line, lint, parse, parse_immediate, parse_recovering, BrokenLine, CancelToken,
+    Interpreter, LinkError, Program, RunError, RunStatus,
+};
 use std::collections::BTreeMap;
 use std::fs;
-use std::io::{self, BufRead, Write};
-
-fn format_line(line: &ast::Line) -> String {
-    use ast::Stmt;
-    let stmt_str = match &line.stmt {
-        Stmt::Print(items) => {
-            let parts: Vec<String> = items
-                .iter()
-                .map(|p| match p {
-                    ast::PrintItem::String(s) => format!("\"{}\"", s),
-                    ast::PrintItem::Expr(e) => format_expr(e),
-                })
-                .collect();
-            format!("PRINT {}", parts.join(", "))
-        }
-        Stmt::Let(v, e) => format!("LET {} = {}", v, format_expr(e)),
-        Stmt::LetArray(v, i, e) => format!("LET {}({}) = {}", v, format_expr(i), format_expr(e)),
-        Stmt::Goto(n) => format!("GOTO {}", n),
-        Stmt::If { condition, then_line } => format!("IF {} THEN {}", format_expr(condition), then_line),
-        Stmt::End => "END".to_string(),
-        Stmt::Dim(v, s) => format!("DIM {}({})", v, s),
+use std::io::{self, IsTerminal};
+
+/// Parse a line range such as `100`, `100-200`, `-200` or `100-`. Missing
+/// bounds extend to the start or end of the program.
+fn parse_range(spec: &str) -> Option<(i32, i32)> {
+    let spec = spec.trim();
+    let bound = |s: &str, default: i32| {
+        let s = s.trim();
+        if s.is_empty() {
+            Some(default)
+        } else {
+            s.parse::<i32>().ok()
+        }
+    };
+    let (start, end) = match spec.split_once('-') {
+        Some((start, end)) => (bound(start, i32::MIN)?, bound(end, i32::MAX)?),
+        None if spec.is_empty() => (i32::MIN, i32::MAX),
+        None => {
+            let n = spec.parse::<i32>().ok()?;
+            (n, n)
+        }
     };
-    format!("{} {}", line.number, stmt_str)
+    if start <= end {
+        Some((start, end))
+    } else {
+        None
+    }
 }
 
-fn format_expr(expr: &ast::Expr) -> String {
-    use ast::{BinaryOp, Expr};
-    match expr {
-        Expr::Number(n) => n.to_string(),
-        Expr::Variable(c) => c.to_string(),
-        Expr::ArrayAccess(n, i) => format!("{}({})", n, format_expr(i)),
-        Expr::Binary { left, op, right } => {
-            let op_str = match op {
-                BinaryOp::Add => "+",
-                BinaryOp::Sub => "-",
-                BinaryOp::Mul => "*",
-                BinaryOp::Div => "/",
-                BinaryOp::Eq => "=",
-                BinaryOp::Ne => "<>",
-                BinaryOp::Lt => "<",
-                BinaryOp::Le => "<=",
-                BinaryOp::Gt => ">",
-                BinaryOp::Ge => ">=",
-            };
-            format!("({} {} {})", format_expr(left), op_str, format_expr(right))
+/// Split comma-separated optional integer arguments such as `100, , 5`.
+/// Returns `None` if there are more than `max` fields or one isn't a number.
+fn parse_int_args(args: &str, max: usize) -> Option<Vec<Option<i32>>> {
+    let args = args.trim();
+    if args.is_empty() {
+        return Some(vec![None; max]);
+    }
+    let mut fields = args
+        .split(',')
+        .map(|f| match f.trim() {
+            "" => Some(None),
+            f => f.parse::<i32>().ok().map(Some),
+        })
+        .collect::<Option<Vec<_>>>()?;
+    if fields.len() > max {
+        return None;
+    }
+    fields.resize(max, None);
+    Some(fields)
+}
+
+/// Parse `[new_start[, old_start[, increment]]]`; empty fields take defaults
+fn parse_renum_args(args: &str) -> Option<(i32, Option<i32>, i32)> {
+    let fields = parse_int_args(args, 3)?;
+    let new_start = fields[0].unwrap_or(10);
+    let step = fields[2].unwrap_or(10);
+    if new_start < 0 || step <= 0 {
+        return None;
+    }
+    Some((new_start, fields[1], step))
+}
+
+/// Parse `[start[, step]]` for AUTO
+fn parse_auto_args(args: &str) -> Option<(i32, i32)> {
+    let fields = parse_int_args(args, 2)?;
+    let start = fields[0].unwrap_or(10);
+    let step = fields[1].unwrap_or(10);
+    if start < 0 || step <= 0 {
+        return None;
+    }
+    Some((start, step))
+}
+
+/// Renumber lines from `old_start` onwards to `new_start`, `new_start + step`,
+/// ... and rewrite every jump target. Lines before `old_start` keep their
+/// numbers. Fails without changing anything if a jump target is missing or
+/// the new numbers would collide with the lines that are kept.
+fn renumber(
+    program: &BTreeMap<i32, ast::Line>,
+    new_start: i32,
+    old_start: Option<i32>,
+    step: i32,
+) -> Result<BTreeMap<i32, ast::Line>, String> {
+    let missing = check_targets(program.values());
+    if !missing.is_empty() {
+        let reports: Vec<String> = missing.iter().map(|e| e.to_string()).collect();
+        return Err(reports.join("; "));
+    }
+
+    let old_start = old_start.unwrap_or(i32::MIN);
+    if let Some((&last_kept, _)) = program.range(..old_start).next_back() {
+        if new_start <= last_kept {
+            return Err(format!("new numbers would overlap line {}", last_kept));
+        }
+    }
+
+    let mut mapping = BTreeMap::new();
+    let mut next = Some(new_start);
+    for &old in program.range(old_start..).map(|(n, _)| n) {
+        let new = next.ok_or("line numbers would overflow")?;
+        mapping.insert(old, new);
+        next = new.checked_add(step);
+    }
+
+    let mut renumbered = BTreeMap::new();
+    for line in program.values() {
+        let mut line = line.clone();
+        line.number = *mapping.get(&line.number).unwrap_or(&line.number);
+        for target in line.stmt.line_refs_mut() {
+            *target = *mapping.get(target).unwrap_or(target);
+        }
+        renumbered.insert(line.number, line);
+    }
+    Ok(renumbered)
+}
+
+/// Number of lines LIST shows before pausing on an interactive terminal
+const PAGE_SIZE: usize = 20;
+
+/// Print program lines, pausing after every page when attached to a terminal
+fn show_lines(editor: &mut LineEditor, lines: impl Iterator<Item = String>) {
+    let paged = io::stdin().is_terminal() && io::stdout().is_terminal();
+    for (i, line) in lines.enumerate() {
+        if paged && i > 0 && i % PAGE_SIZE == 0 {
+            match editor.read_line("-- More -- (Enter to continue, Q to stop) ") {
+                Input::Line(answer) if !answer.trim().eq_ignore_ascii_case("Q") => {}
+                _ => break,
+            }
+        }
+        println!("{}", line);
+    }
+}
+
+/// The program text in `start..=end` as LIST and SAVE show it: parsed lines
+/// in canonical form and broken lines as they were written
+fn listing(
+    program: &BTreeMap<i32, ast::Line>,
+    broken: &BTreeMap<i32, BrokenLine>,
+    start: i32,
+    end: i32,
+) -> BTreeMap<i32, String> {
+    let mut listing: BTreeMap<i32, String> = program.range(start..=end).map(|(&n, l)| (n, format_line(l))).collect();
+    listing.extend(broken.range(start..=end).map(|(&n, b)| (n, b.text.clone())));
+    listing
+}
+
+/// Refuse to run while LOAD-ed lines are still broken; returns whether any are
+fn report_broken(broken: &BTreeMap<i32, BrokenLine>) -> bool {
+    if !broken.is_empty() {
+        let numbers: Vec<String> = broken.keys().map(|n| n.to_string()).collect();
+        eprintln!("Broken lines {} - fix them with EDIT or delete them first", numbers.join(", "));
+    }
+    !broken.is_empty()
+}
+
+/// AUTO mode: prompt with successive line numbers and store each line
+/// entered until an empty line, end of input or Ctrl-C. A line that fails
+/// to parse is reported and its number offered again. Returns the number
+/// of lines stored.
+fn auto_input(editor: &mut LineEditor, program: &mut BTreeMap<i32, ast::Line>, start: i32, step: i32) -> usize {
+    let mut stored = 0;
+    let mut number = Some(start);
+
+    while let Some(n) = number {
+        if program.contains_key(&n) {
+            println!("Warning: line {} already exists and will be replaced", n);
+        }
+        let Input::Line(text) = editor.read_line(&format!("{} ", n)) else {
+            break;
+        };
+        let text = text.trim();
+        if text.is_empty() {
+            break;
+        }
+        let source = format!("{} {}", n, text);
+        match parse(&source) {
+            Ok(lines) => {
+                for line in lines {
+                    program.insert(line.number, line);
+                    stored += 1;
+                }
+                number = n.checked_add(step);
+            }
+            Err(e) => eprintln!("Parse error: {}", e.report(None, &source)),
+        }
+    }
+    stored
+}
+
+fn new_session(program: Vec<ast::Line>, interrupt: &CancelToken) -> Interpreter {
+    let mut interp = Interpreter::new(Program::new(program));
+    interp.set_cancel_token(interrupt.clone());
+    interp
+}
+
+/// Report jumps to missing lines found before running; never resumable
+fn report_link(errors: &[LinkError]) -> bool {
+    for e in errors {
+        eprintln!("Link error: {}", e);
+    }
+    false
+}
+
+/// Report how a run ended and whether it can be resumed with CONT
+fn report_run(result: Result<RunStatus, RunError>) -> bool {
+    match result {
+        Ok(RunStatus::Ended) => false,
+        Ok(RunStatus::Stopped(n)) => {
+            println!("Stopped at line {}", n);
+            true
+        }
+        Ok(RunStatus::Interrupted(n)) => {
+            println!("Break in {}", n);
+            true
+        }
+        Ok(RunStatus::WaitingForInput(n)) => {
+            println!("Waiting for input in {}", n);
+            true
+        }
+        Ok(status) => {
+            println!("{}", status);
+            true
+        }
+        Err(e) => {
+            eprintln!("Runtime error: {}", e);
+            false
         }
     }
 }
 
 fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        Some("repl") if args.len() == 1 => repl(),
+        Some(_) => std::process::exit(cli::main(&args)),
+        None if io::stdin().is_terminal() => repl(),
+        None => std::process::exit(cli::main(&["-".to_string()])),
+    }
+}
+
+fn repl() {
     println!("Tiny BASIC Interpreter");
-    println!("Commands: LOAD, SAVE, RUN, LIST, NEW, QUIT");
+    println!("Commands: LOAD, SAVE, RUN, CONT, LIST, EDIT, NEW, QUIT");
     println!();
 
     let mut program: BTreeMap<i32, ast::Line> = BTreeMap::new();
+    // Lines kept by `LOAD "file", KEEP` that failed to parse
+    let mut broken: BTreeMap<i32, BrokenLine> = BTreeMap::new();
+    // A `#!` line from the LOAD-ed file, written back by SAVE so scripts
+    // stay executable
+    let mut shebang: Option<String> = None;
+
+    // Ctrl-C cancels the running program, which reports "Break in <line>"
+    // and returns to the prompt; CONT resumes it
+    let interrupt = CancelToken::new();
+    {
+        let interrupt = interrupt.clone();
+        if let Err(e) = ctrlc::set_handler(move || interrupt.cancel()) {
+            eprintln!("Warning: Ctrl-C handling unavailable: {}", e);
+        }
+    }
 
-    let stdin = io::stdin();
-    let mut stdout = io::stdout();
+    // The interpreter from the last RUN stays alive so that a stopped
+    // program keeps its variables, can be resumed with CONT, and shares
+    // them with statements typed in immediate mode.
+    let mut session = new_session(Vec::new(), &interrupt);
+    let mut can_continue = false;
 
-    loop {
-        print!("> ");
-        stdout.flush().unwrap();
+    let mut editor = LineEditor::new();
+    // Text for the next prompt to start with, set by EDIT
+    let mut pending_edit: Option<String> = None;
 
-        let mut input = String::new();
-        if stdin.lock().read_line(&mut input).is_err() {
-            break;
-        }
+    loop {
+        let input = match pending_edit.take() {
+            Some(text) => editor.read_line_with("> ", &text),
+            None => editor.read_line("> "),
+        };
+        let input = match input {
+            Input::Line(line) => line,
+            Input::Interrupted => continue,
+            Input::Eof => break,
+        };
         let input = input.trim();
 
         if input.is_empty() {
             continue;
         }
+        editor.add_history(input);
 
         let input_upper = input.to_uppercase();
 
@@ -96,49 +315,142 @@ fn main() {
         }
         if input_upper == "NEW" {
             program.clear();
+            broken.clear();
+            shebang = None;
+            session = new_session(Vec::new(), &interrupt);
+            can_continue = false;
             println!("Program cleared.");
             continue;
         }
-        if input_upper == "LIST" {
-            if program.is_empty() {
+        if input_upper == "LIST" || input_upper.starts_with("LIST ") {
+            if program.is_empty() && broken.is_empty() {
                 println!("(No program)");
             } else {
-                for line in program.values() {
-                    println!("{}", format_line(line));
+                match parse_range(&input[4..]) {
+                    Some((start, end)) => {
+                        let lines = listing(&program, &broken, start, end);
+                        show_lines(&mut editor, lines.into_iter().map(|(n, text)| match broken.get(&n) {
+                            Some(b) => format!("{}    <- {}", text, b.error),
+                            None => text,
+                        }))
+                    }
+                    None => eprintln!("Usage: LIST [start-end]"),
                 }
             }
             continue;
         }
+        if input_upper.starts_with("EDIT ") {
+            match input[5..].trim().parse::<i32>() {
+                Ok(n) => match (program.get(&n), broken.get(&n)) {
+                    (Some(line), _) => pending_edit = Some(format_line(line)),
+                    (None, Some(b)) => pending_edit = Some(b.text.clone()),
+                    (None, None) => eprintln!("Line {} not found", n),
+                },
+                Err(_) => eprintln!("Usage: EDIT line"),
+            }
+            continue;
+        }
+        if input_upper.starts_with("FIND ") {
+            let query = input[5..].trim();
+            let target = query
+                .get(..4)
+                .filter(|kw| kw.eq_ignore_ascii_case("GOTO"))
+                .map(|_| query[4..].trim().parse::<i32>());
+            let matches: Vec<&ast::Line> = match target {
+                Some(Ok(n)) => program
+                    .values()
+                    .filter(|l| l.stmt.line_refs().contains(&n))
+                    .collect(),
+                Some(Err(_)) => {
+                    eprintln!("Usage: FIND \"text\" | FIND GOTO line");
+                    continue;
+                }
+                None => {
+                    let needle = query.trim_matches('"').to_uppercase();
+                    program
+                        .values()
+                        .filter(|l| format_line(l).to_uppercase().contains(&needle))
+                        .collect()
+                }
+            };
+            if matches.is_empty() {
+                println!("(No matching lines)");
+            } else {
+                show_lines(&mut editor, matches.into_iter().map(format_line));
+            }
+            continue;
+        }
         if input_upper == "RUN" {
             let lines: Vec<_> = program.values().cloned().collect();
             if lines.is_empty() {
                 println!("(No program to run)");
-            } else {
-                match parse_program(&lines) {
-                    Ok(parsed) => {
-                        let mut interp = Interpreter::new(parsed);
-                        if let Err(e) = interp.run() {
-                            eprintln!("Runtime error: {}", e);
-                        }
+            } else if !report_broken(&broken) {
+                session = new_session(lines, &interrupt);
+                can_continue = match session.link() {
+                    Ok(()) => {
+                        interrupt.reset();
+                        report_run(session.run())
                     }
-                    Err(e) => eprintln!("Parse error: {}", e),
-                }
+                    Err(errors) => report_link(&errors),
+                };
+            }
+            continue;
+        }
+        if input_upper == "CHECK" {
+            let lints = lint(&Program::new(program.values().cloned().collect()));
+            if lints.is_empty() {
+                println!("No problems found.");
+            }
+            for l in lints {
+                println!("{}", l);
+            }
+            continue;
+        }
+        if input_upper == "CONT" {
+            if can_continue {
+                interrupt.reset();
+                can_continue = report_run(session.cont());
+            } else {
+                eprintln!("Can't continue");
             }
             continue;
         }
         if input_upper.starts_with("LOAD ") {
-            let path = input[5..].trim().trim_matches('"');
+            // LOAD "file", KEEP loads the valid lines even if others are broken
+            let arg = input[5..].trim();
+            let (arg, keep) = match arg.rsplit_once(',') {
+                Some((path, flag)) if flag.trim().eq_ignore_ascii_case("KEEP") => (path, true),
+                _ => (arg, false),
+            };
+            let path = arg.trim().trim_matches('"');
             match fs::read_to_string(path) {
                 Ok(contents) => {
-                    match parse(&contents) {
-                        Ok(lines) => {
-                            program.clear();
-                            for line in lines {
-                                program.insert(line.number, line);
+                    let (lines, errors) = parse_recovering(&contents);
+                    for b in &errors {
+                        eprintln!("{}", b.error.report(Some(path), &contents));
+                    }
+                    if errors.is_empty() || keep {
+                        program.clear();
+                        broken.clear();
+                        shebang = contents.lines().next().filter(|l| l.starts_with("#!")).map(str::to_string);
+                        can_continue = false;
+                        for line in lines {
+                            program.insert(line.number, line);
+                        }
+                        // Lines without a number can't be edited, so they are dropped
+                        for b in errors {
+                            if let Some(n) = b.number {
+                                program.remove(&n);
+                                broken.insert(n, b);
                             }
-                            println!("Loaded {} lines from {}", program.len(), path);
                         }
-                        Err(e) => eprintln!("Parse error: {}", e),
+                        println!("Loaded {} lines from {}", program.len(), path);
+                        if !broken.is_empty() {
+                            let s = if broken.len() == 1 { "" } else { "s" };
+                            println!("Kept {} broken line{}; LIST shows them and EDIT fixes them", broken.len(), s);
+                        }
+                    } else {
+                        eprintln!("Nothing loaded; use LOAD \"{}\", KEEP to keep the valid lines", path);
                     }
                 }
                 Err(e) => eprintln!("Error loading file: {}", e),
@@ -147,37 +459,114 @@ fn main() {
         }
         if input_upper.starts_with("SAVE ") {
             let path = input[5..].trim().trim_matches('"');
+            let lines = listing(&program, &broken, i32::MIN, i32::MAX);
             let mut content = String::new();
-            for line in program.values() {
-                content.push_str(&format_line(line));
+            for text in shebang.iter().chain(lines.values()) {
+                content.push_str(text);
                 content.push('\n');
             }
             match fs::write(path, content) {
-                Ok(_) => println!("Saved {} lines to {}", program.len(), path),
+                Ok(_) => println!("Saved {} lines to {}", lines.len(), path),
                 Err(e) => eprintln!("Error saving file: {}", e),
             }
             continue;
         }
 
+        if input_upper == "DELETE" || input_upper.starts_with("DELETE ") {
+            match parse_range(&input[6..]) {
+                Some((start, end)) if !input[6..].trim().is_empty() => {
+                    let doomed: Vec<i32> = listing(&program, &broken, start, end).into_keys().collect();
+                    for n in &doomed {
+                        program.remove(n);
+                        broken.remove(n);
+                    }
+                    can_continue = false;
+                    println!("Deleted {} line{}.", doomed.len(), if doomed.len() == 1 { "" } else { "s" });
+                }
+                _ => eprintln!("Usage: DELETE line | DELETE start-end"),
+            }
+            continue;
+        }
+
+        if input_upper == "RENUM" || input_upper.starts_with("RENUM ") {
+            match parse_renum_args(&input[5..]) {
+                Some(_) if report_broken(&broken) => {}
+                Some((new_start, old_start, step)) => {
+                    match renumber(&program, new_start, old_start, step) {
+                        Ok(renumbered) => {
+                            program = renumbered;
+                            can_continue = false;
+                            println!("Renumbered {} lines.", program.len());
+                        }
+                        Err(e) => eprintln!("RENUM failed: {}", e),
+                    }
+                }
+                None => eprintln!("Usage: RENUM [new_start[, old_start[, increment]]]"),
+            }
+            continue;
+        }
+
+        if input_upper == "AUTO" || input_upper.starts_with("AUTO ") {
+            match parse_auto_args(&input[4..]) {
+                Some((start, step)) => {
+                    if auto_input(&mut editor, &mut program, start, step) > 0 {
+                        broken.retain(|n, _| !program.contains_key(n));
+                        can_continue = false;
+                    }
+                }
+                None => eprintln!("Usage: AUTO [start[, step]]"),
+            }
+            continue;
+        }
+
+        // A bare line number deletes that line
+        if let Ok(number) = input.parse::<i32>() {
+            if program.remove(&number).is_some() | broken.remove(&number).is_some() {
+                can_continue = false;
+            } else {
+                eprintln!("Line {} not found", number);
+            }
+            continue;
+        }
+
+        // Anything without a line number is executed immediately
+        if !input.starts_with(|c: char| c.is_ascii_digit()) {
+            match parse_immediate(input) {
+                Ok(stmt) => match session.execute_immediate(&stmt) {
+                    Ok(Some(_)) if report_broken(&broken) => {}
+                    Ok(Some(target)) => {
+                        // GOTO/IF from the prompt starts the current program
+                        // at that line without clearing variables
+                        session.set_program(Program::new(program.values().cloned().collect()));
+                        can_continue = match session.link() {
+                            Ok(()) => {
+                                interrupt.reset();
+                                report_run(session.goto(target))
+                            }
+                            Err(errors) => report_link(&errors),
+                        };
+                    }
+                    Ok(None) => {}
+                    Err(e) => eprintln!("Runtime error: {}", e),
+                },
+                Err(e) => eprintln!("Parse error: {}", e.report(None, input)),
+            }
+            continue;
+        }
+
         // Try to parse as program line (NUMBER STATEMENT)
         match parse(input) {
             Ok(lines) => {
                 for line in lines {
+                    broken.remove(&line.number);
                     program.insert(line.number, line);
                 }
+                can_continue = false;
             }
             Err(e) => {
-                eprintln!("Parse error: {:?}", e);
+                eprintln!("Parse error: {}", e.report(None, input));
             }
         }
     }
-}
-
-fn parse_program(lines: &[ast::Line]) -> Result<Vec<ast::Line>, parser::ParseError> {
-    let mut buf = String::new();
-    for line in lines {
-        buf.push_str(&format_line(line));
-        buf.push('\n');
-    }
-    parse(&buf)
+    editor.save_history();
 }
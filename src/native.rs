@@ -0,0 +1,102 @@
+/// Native functions for Tiny BASIC - host Rust code callable from programs
+///
+/// A host registers a function under a name such as `TEMP` or `LOG$`
+/// together with its parameter and return types. Programs call it like
+/// `PRINT TEMP(3)`; names ending in `$` return strings, all others integers.
+use std::collections::HashMap;
+use std::fmt;
+
+/// A value passed to or returned from a native function
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Int(i32),
+    Str(String),
+}
+
+impl Value {
+    pub fn value_type(&self) -> ValueType {
+        match self {
+            Value::Int(_) => ValueType::Int,
+            Value::Str(_) => ValueType::Str,
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Int(n) => write!(f, "{}", n),
+            Value::Str(s) => write!(f, "{}", s),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ValueType {
+    Int,
+    Str,
+}
+
+impl fmt::Display for ValueType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValueType::Int => write!(f, "number"),
+            ValueType::Str => write!(f, "string"),
+        }
+    }
+}
+
+/// The Rust side of a native function. An `Err` message is reported to the
+/// program as a runtime error.
+pub type NativeFn = Box<dyn Fn(&[Value]) -> Result<Value, String> + Send>;
+
+pub struct NativeFunction {
+    pub params: Vec<ValueType>,
+    pub returns: ValueType,
+    pub func: NativeFn,
+}
+
+/// Functions registered by the host, keyed by upper-case name
+#[derive(Default)]
+pub struct Registry {
+    functions: HashMap<String, NativeFunction>,
+}
+
+impl Registry {
+    /// Register `func` under `name`, replacing any earlier registration.
+    ///
+    /// # Panics
+    ///
+    /// If `name` is not a valid function name: a letter followed by at least
+    /// one more letter or digit, plus a trailing `$` exactly when `returns`
+    /// is `ValueType::Str`.
+    pub fn register(&mut self, name: &str, params: &[ValueType], returns: ValueType, func: NativeFn) {
+        let name = name.to_ascii_uppercase();
+        let base = name.strip_suffix('$').unwrap_or(&name);
+        assert!(
+            base.len() >= 2
+                && base.starts_with(|c: char| c.is_ascii_alphabetic())
+                && base.chars().all(|c| c.is_ascii_alphanumeric()),
+            "invalid native function name: {}",
+            name
+        );
+        assert_eq!(
+            name.ends_with('$'),
+            returns == ValueType::Str,
+            "native function {}: names ending in $ must return strings and others numbers",
+            name
+        );
+        self.functions.insert(
+            name,
+            NativeFunction {
+                params: params.to_vec(),
+                returns,
+                func,
+            },
+        );
+    }
+
+    pub fn get(&self, name: &str) -> Option<&NativeFunction> {
+        self.functions.get(name)
+    }
+}
@@ -0,0 +1,107 @@
+/// Optimizer for Tiny BASIC - folds constants and simplifies expressions
+///
+/// The pass is optional and only changes how much work a program does, never
+/// what it does. Anything that can fail at run time - division by zero,
+/// array bounds, native calls - is left in place, so the error is still
+/// raised by the same line as in the unoptimized program. Arithmetic that
+/// would overflow `i32` isn't folded either, so it still stops the program
+/// with `RuntimeError::Overflow` on its line.
+use crate::ast::{BinaryOp, Expr};
+use crate::program::Program;
+
+/// A copy of `program` with every expression folded
+pub fn optimize(program: &Program) -> Program {
+    let lines = program
+        .lines()
+        .iter()
+        .map(|line| {
+            let mut line = line.clone();
+            for expr in line.stmt.exprs_mut() {
+                *expr = fold(std::mem::replace(expr, Expr::Number(0)));
+            }
+            line
+        })
+        .collect();
+    Program::new(lines)
+}
+
+/// Evaluate constant subexpressions and drop identities such as `X + 0`
+/// and `X * 1`. `2 * 3 + X` becomes `6 + X` and `-(5)` becomes `-5`, but
+/// `- -X` stays, since negating X overflows when it is the smallest `i32`.
+pub fn fold(expr: Expr) -> Expr {
+    match expr {
+        Expr::Neg(operand) => match fold(*operand) {
+            Expr::Number(n) if n != i32::MIN => Expr::Number(-n),
+            operand => Expr::Neg(Box::new(operand)),
+        },
+        Expr::Binary { left, op, right } => binary(fold(*left), op, fold(*right)),
+        Expr::ArrayAccess(name, index) => Expr::ArrayAccess(name, Box::new(fold(*index))),
+        Expr::Argv(index) => Expr::Argv(Box::new(fold(*index))),
+        Expr::Call(name, args) => Expr::Call(name, args.into_iter().map(fold).collect()),
+        expr => expr,
+    }
+}
+
+/// Fold one operation on already folded operands
+fn binary(left: Expr, op: BinaryOp, right: Expr) -> Expr {
+    if let (Expr::Number(l), Expr::Number(r)) = (&left, &right) {
+        if let Some(n) = constant(op, *l, *r) {
+            return Expr::Number(n);
+        }
+    }
+    match (op, left, right) {
+        // Dropping the arithmetic would also drop its check that `x` is a
+        // number, e.g. for a string-valued native call
+        (op, left, right) if !(is_numeric(&left) && is_numeric(&right)) => Expr::Binary {
+            left: Box::new(left),
+            op,
+            right: Box::new(right),
+        },
+        (BinaryOp::Add, x, Expr::Number(0)) | (BinaryOp::Add, Expr::Number(0), x) => x,
+        (BinaryOp::Sub, x, Expr::Number(0)) => x,
+        (BinaryOp::Sub, Expr::Number(0), x) => Expr::Neg(Box::new(x)),
+        (BinaryOp::Mul, x, Expr::Number(1)) | (BinaryOp::Mul, Expr::Number(1), x) => x,
+        // `X * 0` is 0 only if evaluating X can't fail
+        (BinaryOp::Mul, x, Expr::Number(0)) | (BinaryOp::Mul, Expr::Number(0), x) if is_infallible(&x) => {
+            Expr::Number(0)
+        }
+        (BinaryOp::Div, x, Expr::Number(1)) => x,
+        (op, left, right) => Expr::Binary {
+            left: Box::new(left),
+            op,
+            right: Box::new(right),
+        },
+    }
+}
+
+/// The result of an operation on two numbers, or `None` if it would fail
+/// at run time and so must be left for the interpreter to report
+fn constant(op: BinaryOp, l: i32, r: i32) -> Option<i32> {
+    match op {
+        BinaryOp::Add => l.checked_add(r),
+        BinaryOp::Sub => l.checked_sub(r),
+        BinaryOp::Mul => l.checked_mul(r),
+        BinaryOp::Div => l.checked_div(r),
+        BinaryOp::Eq => Some((l == r) as i32),
+        BinaryOp::Ne => Some((l != r) as i32),
+        BinaryOp::Lt => Some((l < r) as i32),
+        BinaryOp::Le => Some((l <= r) as i32),
+        BinaryOp::Gt => Some((l > r) as i32),
+        BinaryOp::Ge => Some((l >= r) as i32),
+    }
+}
+
+/// Whether `expr` can only evaluate to a number (or fail). Strings and
+/// native calls, which may return strings, can't.
+fn is_numeric(expr: &Expr) -> bool {
+    matches!(
+        expr,
+        Expr::Number(_) | Expr::Variable(_) | Expr::ArrayAccess(..) | Expr::Neg(_) | Expr::Argc | Expr::Argv(_)
+            | Expr::Binary { .. }
+    )
+}
+
+/// Whether evaluating `expr` always succeeds; variables always exist
+fn is_infallible(expr: &Expr) -> bool {
+    matches!(expr, Expr::Number(_) | Expr::Variable(_) | Expr::Argc)
+}
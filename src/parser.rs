@@ -1,17 +1,37 @@
 /// Parser for Tiny BASIC - builds AST from tokens
 use crate::ast::{BinaryOp, Expr, Line, PrintItem, Stmt};
-use crate::lexer::{Lexer, LexerError, Token};
+use crate::diagnostic;
+use crate::lexer::{Lexer, LexerError, Span, SpannedToken, Token};
 use std::fmt;
-use std::iter::Peekable;
-use std::vec::IntoIter;
 
 #[derive(Debug)]
-#[allow(dead_code)]
+#[non_exhaustive]
 pub enum ParseError {
     Lexer(LexerError),
-    UnexpectedEnd,
-    UnexpectedToken(String),
-    InvalidLineNumber,
+    /// The input ended where `expected` should have been, which reads as
+    /// the end of the last line
+    UnexpectedEnd { expected: String, span: Span },
+    /// `found` appeared where `expected` should have been
+    UnexpectedToken { expected: String, found: String, span: Span },
+    UnknownStatement { name: String, span: Span },
+}
+
+impl ParseError {
+    /// Where in the source the error was found
+    pub fn span(&self) -> Span {
+        match self {
+            ParseError::Lexer(e) => e.span,
+            ParseError::UnexpectedEnd { span, .. }
+            | ParseError::UnexpectedToken { span, .. }
+            | ParseError::UnknownStatement { span, .. } => *span,
+        }
+    }
+
+    /// The message with its location and the offending source line, as
+    /// produced by `diagnostic::render`
+    pub fn report(&self, file: Option<&str>, source: &str) -> String {
+        diagnostic::render(file, source, self.span(), &self.to_string())
+    }
 }
 
 impl From<LexerError> for ParseError {
@@ -24,60 +44,110 @@ impl fmt::Display for ParseError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             ParseError::Lexer(e) => write!(f, "{}", e),
-            ParseError::UnexpectedEnd => write!(f, "Unexpected end of input"),
-            ParseError::UnexpectedToken(s) => write!(f, "{}", s),
-            ParseError::InvalidLineNumber => write!(f, "Invalid line number"),
+            ParseError::UnexpectedEnd { expected, .. } => write!(f, "Expected {}, found end of line", expected),
+            ParseError::UnexpectedToken { expected, found, .. } => write!(f, "Expected {}, found {}", expected, found),
+            ParseError::UnknownStatement { name, .. } => write!(f, "Unknown statement: {}", name),
         }
     }
 }
 
+/// A program line that failed to parse, kept so it can be shown and fixed
+#[derive(Debug)]
+pub struct BrokenLine {
+    /// The BASIC line number, if the line starts with one
+    pub number: Option<i32>,
+    /// The line as written in the source
+    pub text: String,
+    pub error: ParseError,
+}
+
+/// A parse error with the source line and BASIC line number (if any) of
+/// the line it was found in
+type LineError = (usize, Option<i32>, ParseError);
+
 pub struct Parser {
-    tokens: Peekable<IntoIter<Token>>,
+    tokens: Vec<SpannedToken>,
+    pos: usize,
+    /// Span of the token most recently consumed
+    span: Span,
+    /// Where the input ends
+    end: Span,
 }
 
 impl Parser {
-    pub fn new(tokens: Vec<Token>) -> Self {
+    /// `end` is where the input ends, reported when a statement is cut short
+    pub fn new(tokens: Vec<SpannedToken>, end: Span) -> Self {
         Self {
-            tokens: tokens.into_iter().peekable(),
+            tokens,
+            pos: 0,
+            span: end,
+            end,
         }
     }
 
     fn advance(&mut self) -> Option<Token> {
-        self.tokens.next()
+        let next = self.tokens.get(self.pos)?;
+        self.pos += 1;
+        self.span = next.span;
+        Some(next.token.clone())
     }
 
     fn peek(&mut self) -> Option<&Token> {
-        self.tokens.peek()
+        self.tokens.get(self.pos).map(|t| &t.token)
+    }
+
+    /// Error for finding `found` (just consumed, or nothing at all) where
+    /// `expected` should be
+    fn unexpected(&self, expected: &str, found: Option<Token>) -> ParseError {
+        match found {
+            Some(t) => ParseError::UnexpectedToken {
+                expected: expected.to_string(),
+                found: t.describe(),
+                span: self.span,
+            },
+            None => ParseError::UnexpectedEnd {
+                expected: expected.to_string(),
+                span: self.end,
+            },
+        }
     }
 
-    fn parse_line(&mut self) -> Result<Option<Line>, ParseError> {
+    fn parse_line(&mut self) -> Result<Line, ParseError> {
         // Line format: NUMBER STATEMENT
-        let line_num = match self.peek().cloned() {
-            Some(Token::Number(n)) => {
-                self.advance();
-                n
-            }
-            Some(_) => return Ok(None),
-            None => return Ok(None),
+        let line_num = match self.advance() {
+            Some(Token::Number(n)) => n,
+            other => return Err(self.unexpected("a line number", other)),
         };
 
         let stmt = self.parse_statement()?;
-        Ok(Some(Line {
+        self.expect_end_of_line()?;
+        Ok(Line {
             number: line_num,
             stmt,
-        }))
+        })
+    }
+
+    /// Anything left on the line after a complete statement is an error,
+    /// e.g. the `Y` in `PRINT X Y`
+    fn expect_end_of_line(&mut self) -> Result<(), ParseError> {
+        match self.advance() {
+            None | Some(Token::Newline) => Ok(()),
+            other => Err(self.unexpected("end of line", other)),
+        }
     }
 
     fn parse_statement(&mut self) -> Result<Stmt, ParseError> {
         match self.advance() {
             Some(Token::Print) => self.parse_print(),
+            Some(Token::Input) => self.parse_input(),
             Some(Token::Let) => self.parse_let(),
             Some(Token::Goto) => self.parse_goto(),
             Some(Token::If) => self.parse_if(),
             Some(Token::End) => Ok(Stmt::End),
+            Some(Token::Stop) => Ok(Stmt::Stop),
+            Some(Token::Name(name)) => Err(ParseError::UnknownStatement { name, span: self.span }),
             Some(Token::Dim) => self.parse_dim(),
-            Some(t) => Err(ParseError::UnexpectedToken(format!("Expected statement, got {:?}", t))),
-            None => Err(ParseError::UnexpectedEnd),
+            other => Err(self.unexpected("a statement", other)),
         }
     }
 
@@ -90,7 +160,13 @@ impl Parser {
                     self.advance();
                     items.push(PrintItem::String(s));
                 }
-                Some(Token::Ident(_)) | Some(Token::Number(_)) | Some(Token::LeftParen) => {
+                Some(Token::Ident(_))
+                | Some(Token::Number(_))
+                | Some(Token::LeftParen)
+                | Some(Token::Minus)
+                | Some(Token::Argc)
+                | Some(Token::Argv)
+                | Some(Token::Name(_)) => {
                     items.push(PrintItem::Expr(self.parse_expr()?));
                 }
                 Some(Token::Comma) => {
@@ -110,11 +186,37 @@ impl Parser {
         Ok(Stmt::Print(items))
     }
 
+    fn parse_input(&mut self) -> Result<Stmt, ParseError> {
+        // INPUT ["prompt",] var[, var...]
+        let prompt = match self.peek().cloned() {
+            Some(Token::String(s)) => {
+                self.advance();
+                self.expect_token(Token::Comma)?;
+                Some(s)
+            }
+            _ => None,
+        };
+
+        let mut vars = Vec::new();
+        loop {
+            match self.advance() {
+                Some(Token::Ident(c)) => vars.push(c),
+                other => return Err(self.unexpected("a variable", other)),
+            }
+            if matches!(self.peek(), Some(Token::Comma)) {
+                self.advance();
+            } else {
+                break;
+            }
+        }
+
+        Ok(Stmt::Input { prompt, vars })
+    }
+
     fn parse_let(&mut self) -> Result<Stmt, ParseError> {
         let var = match self.advance() {
             Some(Token::Ident(c)) => c,
-            Some(t) => return Err(ParseError::UnexpectedToken(format!("Expected variable, got {:?}", t))),
-            None => return Err(ParseError::UnexpectedEnd),
+            other => return Err(self.unexpected("a variable", other)),
         };
 
         if matches!(self.peek(), Some(Token::LeftParen)) {
@@ -135,16 +237,14 @@ impl Parser {
     fn expect_token(&mut self, expected: Token) -> Result<(), ParseError> {
         match self.advance() {
             Some(t) if std::mem::discriminant(&t) == std::mem::discriminant(&expected) => Ok(()),
-            Some(t) => Err(ParseError::UnexpectedToken(format!("Expected {:?}, got {:?}", expected, t))),
-            None => Err(ParseError::UnexpectedEnd),
+            other => Err(self.unexpected(&expected.describe(), other)),
         }
     }
 
     fn parse_goto(&mut self) -> Result<Stmt, ParseError> {
         let line = match self.advance() {
             Some(Token::Number(n)) => n,
-            Some(t) => return Err(ParseError::UnexpectedToken(format!("Expected line number, got {:?}", t))),
-            None => return Err(ParseError::UnexpectedEnd),
+            other => return Err(self.unexpected("a line number", other)),
         };
         Ok(Stmt::Goto(line))
     }
@@ -154,8 +254,7 @@ impl Parser {
         self.expect_token(Token::Then)?;
         let then_line = match self.advance() {
             Some(Token::Number(n)) => n,
-            Some(t) => return Err(ParseError::UnexpectedToken(format!("Expected line number, got {:?}", t))),
-            None => return Err(ParseError::UnexpectedEnd),
+            other => return Err(self.unexpected("a line number", other)),
         };
         Ok(Stmt::If {
             condition: Box::new(condition),
@@ -166,14 +265,12 @@ impl Parser {
     fn parse_dim(&mut self) -> Result<Stmt, ParseError> {
         let var = match self.advance() {
             Some(Token::Ident(c)) => c,
-            Some(t) => return Err(ParseError::UnexpectedToken(format!("Expected array name, got {:?}", t))),
-            None => return Err(ParseError::UnexpectedEnd),
+            other => return Err(self.unexpected("an array name", other)),
         };
         self.expect_token(Token::LeftParen)?;
         let size = match self.advance() {
             Some(Token::Number(n)) => n,
-            Some(t) => return Err(ParseError::UnexpectedToken(format!("Expected array size, got {:?}", t))),
-            None => return Err(ParseError::UnexpectedEnd),
+            other => return Err(self.unexpected("an array size", other)),
         };
         self.expect_token(Token::RightParen)?;
         Ok(Stmt::Dim(var, size))
@@ -248,11 +345,7 @@ impl Parser {
         if matches!(self.peek(), Some(Token::Minus)) {
             self.advance();
             let expr = self.parse_unary()?;
-            return Ok(Expr::Binary {
-                left: Box::new(Expr::Number(0)),
-                op: BinaryOp::Sub,
-                right: Box::new(expr),
-            });
+            return Ok(Expr::Neg(Box::new(expr)));
         }
         self.parse_primary()
     }
@@ -275,26 +368,140 @@ impl Parser {
                 self.expect_token(Token::RightParen)?;
                 Ok(expr)
             }
-            Some(t) => Err(ParseError::UnexpectedToken(format!("Expected expression, got {:?}", t))),
-            None => Err(ParseError::UnexpectedEnd),
+            Some(Token::String(s)) => Ok(Expr::Str(s)),
+            Some(Token::Name(name)) => {
+                // Native function call: NAME(arg, ...)
+                self.expect_token(Token::LeftParen)?;
+                let mut args = Vec::new();
+                if matches!(self.peek(), Some(Token::RightParen)) {
+                    self.advance();
+                } else {
+                    loop {
+                        args.push(self.parse_expr()?);
+                        match self.advance() {
+                            Some(Token::Comma) => continue,
+                            Some(Token::RightParen) => break,
+                            other => return Err(self.unexpected("',' or ')'", other)),
+                        }
+                    }
+                }
+                Ok(Expr::Call(name, args))
+            }
+            Some(Token::Argc) => Ok(Expr::Argc),
+            Some(Token::Argv) => {
+                self.expect_token(Token::LeftParen)?;
+                let index = self.parse_expr()?;
+                self.expect_token(Token::RightParen)?;
+                Ok(Expr::Argv(Box::new(index)))
+            }
+            other => Err(self.unexpected("an expression", other)),
         }
     }
 
     pub fn parse_program(&mut self) -> Result<Vec<Line>, ParseError> {
+        let (lines, mut errors) = self.parse_program_recovering();
+        if errors.is_empty() {
+            Ok(lines)
+        } else {
+            Err(errors.swap_remove(0).2)
+        }
+    }
+
+    /// Parse every line, skipping to the next source line after an error
+    fn parse_program_recovering(&mut self) -> (Vec<Line>, Vec<LineError>) {
         let mut lines = Vec::new();
+        let mut errors = Vec::new();
         loop {
-            match self.parse_line()? {
-                Some(line) => lines.push(line),
-                None => break,
+            while matches!(self.peek(), Some(Token::Newline)) {
+                self.advance();
+            }
+            if self.peek().is_none() {
+                break;
+            }
+            let start = self.pos;
+            match self.parse_line() {
+                Ok(line) => lines.push(line),
+                Err(e) => {
+                    let first = &self.tokens[start];
+                    let number = match first.token {
+                        Token::Number(n) => Some(n),
+                        _ => None,
+                    };
+                    errors.push((first.span.line, number, e));
+                    self.pos = self.tokens[start..]
+                        .iter()
+                        .position(|t| t.span.line > first.span.line)
+                        .map_or(self.tokens.len(), |i| start + i);
+                }
             }
         }
         lines.sort_by_key(|l| l.number);
-        Ok(lines)
+        (lines, errors)
     }
 }
 
+fn parser(source: &str) -> Result<Parser, ParseError> {
+    let mut lexer = Lexer::new(source);
+    let tokens = lexer.tokenize()?;
+    Ok(new_parser(tokens, &lexer))
+}
+
+fn new_parser(tokens: Vec<SpannedToken>, lexer: &Lexer) -> Parser {
+    // Point just past the last token rather than at a trailing blank line
+    let end = match tokens.last() {
+        Some(last) => Span {
+            column: last.span.column + last.span.len,
+            len: 1,
+            ..last.span
+        },
+        None => lexer.end_span(),
+    };
+    Parser::new(tokens, end)
+}
+
 pub fn parse(source: &str) -> Result<Vec<Line>, ParseError> {
-    let tokens = Lexer::new(source).tokenize()?;
-    let mut parser = Parser::new(tokens);
-    parser.parse_program()
+    parser(source)?.parse_program()
+}
+
+/// Parse a whole program, reporting every broken line instead of stopping
+/// at the first. Returns the lines that parsed and the ones that didn't, in
+/// source order.
+pub fn parse_recovering(source: &str) -> (Vec<Line>, Vec<BrokenLine>) {
+    let mut lexer = Lexer::new(source);
+    let (tokens, lexer_errors) = lexer.tokenize_all();
+    let (lines, mut errors) = new_parser(tokens, &lexer).parse_program_recovering();
+
+    // A line the lexer rejected never reached the parser, so take its
+    // number straight from the source text
+    let text_of = |line: usize| source.lines().nth(line.saturating_sub(1)).unwrap_or("").trim().to_string();
+    for e in lexer_errors {
+        let digits: String = text_of(e.span.line).chars().take_while(|c| c.is_ascii_digit()).collect();
+        errors.push((e.span.line, digits.parse().ok(), ParseError::Lexer(e)));
+    }
+    errors.sort_by_key(|&(line, _, _)| line);
+
+    let broken = errors
+        .into_iter()
+        .map(|(line, number, error)| BrokenLine {
+            number,
+            text: text_of(line),
+            error,
+        })
+        .collect();
+    (lines, broken)
+}
+
+/// Parse a single statement typed without a line number (immediate mode)
+pub fn parse_immediate(source: &str) -> Result<Stmt, ParseError> {
+    let mut parser = parser(source)?;
+    let stmt = parser.parse_statement()?;
+    parser.expect_end_of_line()?;
+    // One statement only: blank lines may follow it, another line may not
+    while parser.peek() == Some(&Token::Newline) {
+        parser.advance();
+    }
+    match parser.advance() {
+        None => Ok(stmt),
+        other => Err(parser.unexpected("end of input", other)),
+    }
 }
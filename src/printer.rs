@@ -0,0 +1,109 @@
+/// Printer for Tiny BASIC - turns the AST back into source text
+///
+/// Expressions are printed with only the parentheses the parser needs to
+/// rebuild the same tree. Output is canonical - uppercase keywords, standard
+/// spacing, no redundant parentheses - and canonical source round-trips
+/// through LOAD and SAVE unchanged.
+use crate::ast::{BinaryOp, Expr, Line, PrintItem, Stmt};
+
+// Binding strength of each grammar level, loosest first
+const PREC_COMPARISON: u8 = 1;
+const PREC_ADDITIVE: u8 = 2;
+const PREC_MULTIPLICATIVE: u8 = 3;
+const PREC_UNARY: u8 = 4;
+const PREC_PRIMARY: u8 = 5;
+
+pub fn format_line(line: &Line) -> String {
+    format!("{} {}", line.number, format_stmt(&line.stmt))
+}
+
+pub fn format_stmt(stmt: &Stmt) -> String {
+    match stmt {
+        Stmt::Print(items) => {
+            let parts: Vec<String> = items
+                .iter()
+                .map(|p| match p {
+                    PrintItem::String(s) => format!("\"{}\"", s),
+                    PrintItem::Expr(e) => format_expr(e),
+                })
+                .collect();
+            if parts.is_empty() {
+                "PRINT".to_string()
+            } else {
+                format!("PRINT {}", parts.join(", "))
+            }
+        }
+        Stmt::Input { prompt, vars } => {
+            let vars: Vec<String> = vars.iter().map(char::to_string).collect();
+            match prompt {
+                Some(p) => format!("INPUT \"{}\", {}", p, vars.join(", ")),
+                None => format!("INPUT {}", vars.join(", ")),
+            }
+        }
+        Stmt::Let(v, e) => format!("LET {} = {}", v, format_expr(e)),
+        Stmt::LetArray(v, i, e) => format!("LET {}({}) = {}", v, format_expr(i), format_expr(e)),
+        Stmt::Goto(n) => format!("GOTO {}", n),
+        Stmt::If { condition, then_line } => format!("IF {} THEN {}", format_expr(condition), then_line),
+        Stmt::End => "END".to_string(),
+        Stmt::Stop => "STOP".to_string(),
+        Stmt::Dim(v, s) => format!("DIM {}({})", v, s),
+    }
+}
+
+pub fn format_expr(expr: &Expr) -> String {
+    format_expr_prec(expr, PREC_COMPARISON)
+}
+
+fn op_info(op: BinaryOp) -> (&'static str, u8) {
+    match op {
+        BinaryOp::Add => ("+", PREC_ADDITIVE),
+        BinaryOp::Sub => ("-", PREC_ADDITIVE),
+        BinaryOp::Mul => ("*", PREC_MULTIPLICATIVE),
+        BinaryOp::Div => ("/", PREC_MULTIPLICATIVE),
+        BinaryOp::Eq => ("=", PREC_COMPARISON),
+        BinaryOp::Ne => ("<>", PREC_COMPARISON),
+        BinaryOp::Lt => ("<", PREC_COMPARISON),
+        BinaryOp::Le => ("<=", PREC_COMPARISON),
+        BinaryOp::Gt => (">", PREC_COMPARISON),
+        BinaryOp::Ge => (">=", PREC_COMPARISON),
+    }
+}
+
+/// Print `expr`, parenthesizing it if it binds more loosely than `min_prec`
+fn format_expr_prec(expr: &Expr, min_prec: u8) -> String {
+    let (text, prec) = match expr {
+        Expr::Number(n) => (n.to_string(), PREC_PRIMARY),
+        Expr::Variable(c) => (c.to_string(), PREC_PRIMARY),
+        Expr::ArrayAccess(n, i) => (format!("{}({})", n, format_expr(i)), PREC_PRIMARY),
+        Expr::Str(s) => (format!("\"{}\"", s), PREC_PRIMARY),
+        Expr::Call(name, args) => {
+            let args: Vec<String> = args.iter().map(format_expr).collect();
+            (format!("{}({})", name, args.join(", ")), PREC_PRIMARY)
+        }
+        Expr::Argc => ("ARGC".to_string(), PREC_PRIMARY),
+        Expr::Argv(i) => (format!("ARGV({})", format_expr(i)), PREC_PRIMARY),
+        Expr::Neg(operand) => (format!("-{}", format_expr_prec(operand, PREC_UNARY)), PREC_UNARY),
+        Expr::Binary { left, op, right } => {
+            let (op_str, prec) = op_info(*op);
+            // Arithmetic is left-associative, so a right operand at the same
+            // level needs parentheses. Comparisons don't chain at all.
+            let (left_min, right_min) = if prec == PREC_COMPARISON {
+                (PREC_ADDITIVE, PREC_ADDITIVE)
+            } else {
+                (prec, prec + 1)
+            };
+            let text = format!(
+                "{} {} {}",
+                format_expr_prec(left, left_min),
+                op_str,
+                format_expr_prec(right, right_min)
+            );
+            (text, prec)
+        }
+    };
+    if prec < min_prec {
+        format!("({})", text)
+    } else {
+        text
+    }
+}
@@ -0,0 +1,61 @@
+/// Program container for Tiny BASIC - the parsed lines in execution order
+use crate::ast::Line;
+use crate::parser::{parse, ParseError};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+#[derive(Debug, Clone, Default)]
+pub struct Program {
+    /// Shared so the interpreter can hold on to a statement while it runs
+    lines: Arc<[Line]>,
+    /// Position of each line number in `lines`, so jumps don't search
+    index: HashMap<i32, usize>,
+}
+
+impl Program {
+    /// Build a program from parsed lines. Lines are sorted by number; for
+    /// duplicate numbers the last one wins, as when typed at the REPL.
+    pub fn new(mut lines: Vec<Line>) -> Self {
+        lines.reverse();
+        lines.sort_by_key(|l| l.number);
+        lines.dedup_by_key(|l| l.number);
+        let index = lines.iter().enumerate().map(|(i, l)| (l.number, i)).collect();
+        Self {
+            lines: lines.into(),
+            index,
+        }
+    }
+
+    /// Parse BASIC source text such as the contents of a `.bas` file
+    pub fn parse(source: &str) -> Result<Self, ParseError> {
+        Ok(Self::new(parse(source)?))
+    }
+
+    pub fn lines(&self) -> &[Line] {
+        &self.lines
+    }
+
+    /// A handle to the lines that stays valid whatever happens to `self`
+    pub(crate) fn shared_lines(&self) -> Arc<[Line]> {
+        Arc::clone(&self.lines)
+    }
+
+    /// Position in `lines()` of the line with this number
+    pub fn index_of(&self, number: i32) -> Option<usize> {
+        self.index.get(&number).copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.lines.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+}
+
+impl From<Vec<Line>> for Program {
+    fn from(lines: Vec<Line>) -> Self {
+        Self::new(lines)
+    }
+}
@@ -0,0 +1,35 @@
+//! A cancel token stops a program running on another thread
+
+use rust_basic::{Interpreter, Program, RunStatus};
+use std::thread;
+use std::time::Duration;
+
+#[test]
+fn cancel_stops_infinite_loop_on_worker_thread() {
+    let mut interp = Interpreter::new(Program::parse("10 LET X = X + 1\n20 GOTO 10\n").unwrap());
+    let token = interp.cancel_token();
+    let worker = thread::spawn(move || {
+        let status = interp.run().unwrap();
+        (status, interp)
+    });
+    thread::sleep(Duration::from_millis(20));
+    token.cancel();
+
+    let (status, mut interp) = worker.join().unwrap();
+    assert!(matches!(status, RunStatus::Interrupted(10 | 20)));
+    assert!(!token.is_cancelled());
+    let before = interp.variable('X').unwrap();
+    assert!(before > 0);
+
+    // CONT resumes where the break happened
+    let resumed = {
+        let token = interp.cancel_token();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(20));
+            token.cancel();
+        });
+        interp.cont().unwrap()
+    };
+    assert!(matches!(resumed, RunStatus::Interrupted(_)));
+    assert!(interp.variable('X').unwrap() > before);
+}
@@ -0,0 +1,142 @@
+//! The command line reports how a program ended through its exit code
+
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+/// What a run of the binary produced: exit code, stdout and stderr
+struct Outcome {
+    code: i32,
+    stdout: String,
+    stderr: String,
+}
+
+/// Run the binary with `args`, feeding it `stdin`
+fn rust_basic(args: &[&str], stdin: &str) -> Outcome {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rust-basic"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to start interpreter");
+    child.stdin.take().unwrap().write_all(stdin.as_bytes()).unwrap();
+    let output = child.wait_with_output().unwrap();
+    Outcome {
+        code: output.status.code().expect("killed by a signal"),
+        stdout: String::from_utf8(output.stdout).unwrap(),
+        stderr: String::from_utf8(output.stderr).unwrap(),
+    }
+}
+
+/// Write `source` to a temporary program file named after `name`
+fn program(name: &str, source: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("rust-basic-cli-{}-{}.bas", std::process::id(), name));
+    fs::write(&path, source).unwrap();
+    path
+}
+
+/// Run a program file with `args` after its name, then delete it
+fn run(name: &str, source: &str, args: &[&str]) -> Outcome {
+    let path = program(name, source);
+    let path_arg = path.to_str().unwrap();
+    let outcome = rust_basic(&[&["run", path_arg], args].concat(), "");
+    fs::remove_file(path).ok();
+    outcome
+}
+
+#[test]
+fn end_exits_0() {
+    let outcome = run("end", "10 PRINT 1 + 1\n20 END\n30 PRINT 3\n", &[]);
+    assert_eq!((outcome.code, outcome.stdout.as_str()), (0, "2\n"));
+}
+
+#[test]
+fn stop_exits_4() {
+    let outcome = run("stop", "10 PRINT 1\n20 STOP\n30 PRINT 2\n", &[]);
+    assert_eq!((outcome.code, outcome.stdout.as_str()), (4, "1\n"));
+    assert_eq!(outcome.stderr, "Stopped at line 20\n");
+}
+
+#[test]
+fn runtime_errors_exit_1() {
+    let outcome = run("div", "10 LET X = 1 / 0\n", &[]);
+    assert_eq!(outcome.code, 1);
+    assert_eq!(outcome.stderr, "Runtime error: Division by zero in line 10: LET X = 1 / 0\n");
+    // A program waiting for INPUT that never comes can't finish either
+    assert_eq!(run("input", "10 INPUT A\n", &[]).code, 1);
+}
+
+#[test]
+fn parse_and_link_errors_exit_2() {
+    assert_eq!(run("parse", "10 LET X 5\n", &[]).code, 2);
+    let outcome = run("link", "10 GOTO 99\n", &[]);
+    assert_eq!(outcome.code, 2);
+    assert!(outcome.stderr.contains("Link error: Undefined line 99 in line 10"), "{}", outcome.stderr);
+}
+
+#[test]
+fn bad_command_lines_exit_3() {
+    assert_eq!(rust_basic(&["run"], "").code, 3);
+    assert_eq!(rust_basic(&["run", "/nonexistent/prog.bas"], "").code, 3);
+    assert_eq!(rust_basic(&["run", "--engine", "fast", "prog.bas"], "").code, 3);
+    assert_eq!(rust_basic(&["--bogus"], "").code, 3);
+
+    let outcome = run("args", "10 PRINT ARGC\n", &["1", "two"]);
+    assert_eq!(outcome.code, 3);
+    assert_eq!(outcome.stderr, "Program arguments must be integers, got 'two'\n");
+}
+
+#[test]
+fn arguments_are_read_with_argc_and_argv() {
+    let outcome = run("argv", "10 PRINT ARGC, ARGV(1) * ARGV(2)\n", &["4", "-9"]);
+    assert_eq!((outcome.code, outcome.stdout.as_str()), (0, "2 -36\n"));
+}
+
+#[test]
+fn execute_one_statement() {
+    let outcome = rust_basic(&["-e", "PRINT 1+1"], "");
+    assert_eq!((outcome.code, outcome.stdout.as_str()), (0, "2\n"));
+    assert_eq!(rust_basic(&["-e", "PRINT 1/0"], "").code, 1);
+    assert_eq!(rust_basic(&["-e", "GOTO 10"], "").code, 1);
+    assert_eq!(rust_basic(&["-e", "PRINT 1 ?"], "").code, 2);
+}
+
+#[test]
+fn check_and_list() {
+    let path = program("check", "20 end\n10 print (1+2)*3\n");
+    let path_arg = path.to_str().unwrap();
+    let outcome = rust_basic(&["check", path_arg], "");
+    assert_eq!((outcome.code, outcome.stdout), (0, format!("{}: OK\n", path_arg)));
+    let outcome = rust_basic(&["list", path_arg], "");
+    assert_eq!((outcome.code, outcome.stdout.as_str()), (0, "10 PRINT (1 + 2) * 3\n20 END\n"));
+    fs::remove_file(path).ok();
+
+    let path = program("check-broken", "10 GOTO 20\n");
+    assert_eq!(rust_basic(&["check", path.to_str().unwrap()], "").code, 2);
+    fs::remove_file(path).ok();
+}
+
+#[test]
+fn programs_can_be_piped_to_stdin() {
+    let source = "10 PRINT 6 * 7\n20 END\n";
+    for args in [&["-"][..], &["run", "-"], &[]] {
+        let outcome = rust_basic(args, source);
+        assert_eq!((outcome.code, outcome.stdout.as_str()), (0, "42\n"), "{:?}", args);
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn shebang_scripts_run_directly() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let source = format!("#!{}\n10 PRINT ARGV(1) + 1\n20 END\n", env!("CARGO_BIN_EXE_rust-basic"));
+    let path = program("shebang", &source);
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+    let output = Command::new(&path).arg("41").output().unwrap();
+    fs::remove_file(path).ok();
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "42\n");
+}
@@ -0,0 +1,84 @@
+//! The bytecode VM must behave exactly like the tree-walker
+
+use rust_basic::console::{CapturedOutput, ScriptedInput};
+use rust_basic::{Engine, Interpreter, Limits, Program, Value, ValueType};
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+/// Everything observable about one run: output, how each `run`/`cont`
+/// call ended, the line hooks' trace and the final variables
+fn observe(source: &str, engine: Engine) -> String {
+    let output = CapturedOutput::new();
+    let trace = Arc::new(Mutex::new(Vec::new()));
+    let mut interp = Interpreter::new(Program::parse(source).unwrap());
+    interp.set_engine(engine);
+    interp.set_output(output.clone());
+    interp.set_input(ScriptedInput::new(["7", "1, 2"]));
+    interp.set_args(vec![4, 9]);
+    interp.set_limits(Limits {
+        max_statements: Some(10_000),
+        ..Limits::default()
+    });
+    interp.register_function("TWICE", &[ValueType::Int], ValueType::Int, |args| match args {
+        [Value::Int(n)] => Ok(Value::Int(n * 2)),
+        _ => unreachable!(),
+    });
+    interp.register_function("TAG$", &[ValueType::Int], ValueType::Str, |args| Ok(Value::Str(format!("<{}>", args[0]))));
+    let before = Arc::clone(&trace);
+    interp.set_before_line_hook(move |_, line| before.lock().unwrap().push(format!("> {}", line.number)));
+    let after = Arc::clone(&trace);
+    interp.set_after_line_hook(move |interp, line| {
+        after.lock().unwrap().push(format!("< {} next {:?}", line.number, interp.current_line()))
+    });
+
+    // Resume after STOP a few times to cover CONT
+    let mut results = vec![format!("{:?}", interp.run())];
+    for _ in 0..3 {
+        results.push(format!("{:?}", interp.cont()));
+    }
+    let variables: Vec<i32> = ('A'..='Z').map(|c| interp.variable(c).unwrap()).collect();
+    let trace = trace.lock().unwrap().join("\n");
+    format!(
+        "output:\n{}results: {:?}\nvariables: {:?}\narrays: {:?}\ntrace:\n{}",
+        output.contents(),
+        results,
+        variables,
+        interp.array('A'),
+        trace
+    )
+}
+
+fn assert_same(source: &str) {
+    assert_eq!(observe(source, Engine::Bytecode), observe(source, Engine::TreeWalker), "{}", source);
+}
+
+#[test]
+fn sample_programs_behave_the_same() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    for file in ["sample.bas", "arrays.bas", "GARY", "tests/programs/precedence.bas"] {
+        assert_same(&fs::read_to_string(root.join(file)).unwrap());
+    }
+}
+
+#[test]
+fn control_flow_and_io_behave_the_same() {
+    assert_same("10 LET I = 1\n20 LET S = S + I * I\n30 LET I = I + 1\n40 IF I <= 100 THEN 20\n50 PRINT S\n");
+    assert_same("10 INPUT A\n20 INPUT \"Two\", B, C\n30 PRINT A, B, C, -A + -(B - C)\n40 END\n50 PRINT 1\n");
+    assert_same("10 PRINT ARGC, ARGV(1) * ARGV(2)\n20 PRINT TWICE(ARGV(2)), TAG$(3)\n30 LET T = TWICE(4) + 1\n");
+    assert_same("10 LET X = 1\n20 STOP\n30 LET X = X + 1\n40 IF X < 3 THEN 20\n50 END\n");
+    assert_same("10 DIM A(10)\n20 LET A(I) = I * I\n30 LET I = I + 1\n40 IF I < 10 THEN 20\n50 PRINT A(9)\n");
+}
+
+#[test]
+fn runtime_errors_behave_the_same() {
+    assert_same("10 LET Y = 1\n20 LET X = Y / Z\n");
+    assert_same("10 DIM A(3)\n20 LET A(I) = 5\n30 LET I = I + 1\n40 GOTO 20\n");
+    assert_same("10 PRINT A(1)\n");
+    assert_same("10 PRINT ARGV(3)\n");
+    assert_same("10 IF 1 THEN 99\n");
+    assert_same("10 LET X = NOPE(1)\n");
+    assert_same("10 LET X = TAG$(1)\n");
+    assert_same("10 INPUT A, B\n");
+    assert_same("10 GOTO 10\n");
+}
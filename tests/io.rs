@@ -0,0 +1,42 @@
+//! Programs driven through captured output and scripted input
+
+use rust_basic::console::{CapturedOutput, ScriptedInput};
+use rust_basic::{Interpreter, Program, RunError, RunStatus, RuntimeError};
+
+fn interpreter(source: &str, input: &[&str]) -> (Interpreter, CapturedOutput) {
+    let output = CapturedOutput::new();
+    let mut interp = Interpreter::new(Program::parse(source).unwrap());
+    interp.set_output(output.clone());
+    interp.set_input(ScriptedInput::new(input.iter().copied()));
+    (interp, output)
+}
+
+#[test]
+fn print_output_is_captured() {
+    let source = std::fs::read_to_string(concat!(env!("CARGO_MANIFEST_DIR"), "/sample.bas")).unwrap();
+    let (mut interp, output) = interpreter(&source, &[]);
+    assert_eq!(interp.run().unwrap(), RunStatus::Ended);
+    assert_eq!(
+        output.contents(),
+        "Hello, Tiny BASIC!\nX = 5 Y = 10\nX + Y = 15\nX is less than Y\n"
+    );
+}
+
+#[test]
+fn input_reads_scripted_values() {
+    let (mut interp, output) = interpreter(
+        "10 INPUT \"Two numbers\", A, B\n20 INPUT C\n30 PRINT A + B + C\n",
+        &["3, 4", "5"],
+    );
+    interp.run().unwrap();
+    assert_eq!(output.contents(), "12\n");
+}
+
+#[test]
+fn input_errors_are_runtime_errors() {
+    let (mut interp, _) = interpreter("10 INPUT A\n", &["x"]);
+    assert!(matches!(interp.run(), Err(RunError { error: RuntimeError::InvalidInput(_), line: Some(10), .. })));
+
+    let (mut interp, _) = interpreter("10 INPUT A\n", &[]);
+    assert!(matches!(interp.run(), Err(RunError { error: RuntimeError::EndOfInput, .. })));
+}
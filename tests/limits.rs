@@ -0,0 +1,71 @@
+//! Each execution limit stops the program with its own error
+
+use rust_basic::{Engine, Interpreter, Limits, Program, RuntimeError, Value, ValueType};
+use std::time::Duration;
+
+fn run_with(source: &str, limits: Limits) -> RuntimeError {
+    let mut interp = Interpreter::new(Program::parse(source).unwrap());
+    interp.set_limits(limits);
+    interp.register_function("REP$", &[ValueType::Int], ValueType::Str, |args| match args {
+        [Value::Int(n)] => Ok(Value::Str("x".repeat(*n as usize))),
+        _ => unreachable!(),
+    });
+    interp.run().unwrap_err().error
+}
+
+#[test]
+fn statement_limit_stops_infinite_loop() {
+    let limits = Limits {
+        max_statements: Some(100),
+        ..Limits::default()
+    };
+    assert!(matches!(run_with("10 GOTO 10\n", limits), RuntimeError::StatementLimitExceeded(100)));
+}
+
+#[test]
+fn timeout_stops_infinite_loop() {
+    let limits = Limits {
+        timeout: Some(Duration::from_millis(50)),
+        ..Limits::default()
+    };
+    assert!(matches!(run_with("10 GOTO 10\n", limits), RuntimeError::TimeLimitExceeded(_)));
+}
+
+#[test]
+fn array_memory_limit_counts_all_arrays() {
+    let limits = Limits {
+        max_array_bytes: Some(400),
+        ..Limits::default()
+    };
+    let err = run_with("10 DIM A(60)\n20 DIM A(100)\n30 DIM B(1)\n", limits);
+    assert!(matches!(err, RuntimeError::ArrayMemoryExceeded { requested: 404, limit: 400 }));
+}
+
+#[test]
+fn string_limit_applies_to_native_results() {
+    let limits = Limits {
+        max_string_len: Some(10),
+        ..Limits::default()
+    };
+    let err = run_with("10 PRINT REP$(10)\n20 PRINT REP$(11)\n", limits);
+    assert!(matches!(err, RuntimeError::StringTooLong { len: 11, limit: 10 }));
+}
+
+#[test]
+fn arithmetic_overflow_is_a_runtime_error() {
+    let cases = [
+        "10 PRINT 2147483647 + 1\n",
+        "10 LET X = -2147483647 - 1\n20 PRINT X / -1\n",
+        "10 LET X = -2147483647 - 1\n20 PRINT -X\n",
+        "10 LET X = 65536\n20 LET Y = X * X\n",
+    ];
+    for source in cases {
+        for engine in [Engine::TreeWalker, Engine::Bytecode] {
+            let mut interp = Interpreter::new(Program::parse(source).unwrap());
+            interp.set_engine(engine);
+            let error = interp.run().unwrap_err();
+            assert!(matches!(error.error, RuntimeError::Overflow), "{}: {}", source, error);
+            assert_eq!(error.line, Some(source.lines().count() as i32 * 10), "{}", source);
+        }
+    }
+}
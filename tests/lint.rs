@@ -0,0 +1,72 @@
+//! The linter finds each kind of likely mistake, and nothing in clean programs
+
+use rust_basic::{lint, lints_to_json, Lint, Program, Warning};
+
+fn lints(source: &str) -> Vec<Lint> {
+    lint(&Program::parse(source).unwrap())
+}
+
+fn warning(line: Option<i32>, warning: Warning) -> Lint {
+    Lint { line, warning }
+}
+
+#[test]
+fn finds_each_kind_of_problem() {
+    let found = lints(
+        "10 DIM B(5)\n20 LET A(1) = 2\n30 LET B(1) = Q + C(1)\n40 IF 1 > 2 THEN 10\n\
+         50 GOTO 99\n60 PRINT \"dead\"\n70 PRINT \"dead too\"\n80 DIM A(3)\n",
+    );
+    assert_eq!(
+        found,
+        [
+            warning(Some(20), Warning::ArrayBeforeDim('A')),
+            warning(Some(30), Warning::ArrayNeverDimensioned('C')),
+            warning(Some(30), Warning::NeverAssigned('Q')),
+            warning(Some(40), Warning::ConditionAlwaysFalse),
+            warning(Some(50), Warning::UndefinedTarget(99)),
+            warning(Some(60), Warning::Unreachable { last: 80 }),
+            warning(None, Warning::MissingEnd),
+        ]
+    );
+}
+
+#[test]
+fn clean_programs_have_no_warnings() {
+    // Runs without error for any input: A(1) to A(9) are in range, and STOP
+    // is reached when N is 0 or less
+    let source = "10 INPUT N\n20 DIM A(10)\n30 LET I = 1\n40 LET A(I) = I * N\n50 LET I = I + 1\n\
+                  60 IF I < 10 THEN 40\n70 IF N > 0 THEN 90\n80 STOP\n90 PRINT A(9)\n100 END\n";
+    assert_eq!(lints(source), []);
+}
+
+#[test]
+fn constant_indices_are_checked_against_dim() {
+    // A(10) is fine once line 40 makes A bigger
+    let found = lints(
+        "5 INPUT I\n10 DIM A(10)\n20 LET A(10) = 1\n30 PRINT A(2 * 4 + 1), A(0 - 1), A(I)\n\
+         40 DIM A(20)\n50 PRINT A(15)\n60 END\n",
+    );
+    assert_eq!(
+        found,
+        [warning(Some(30), Warning::IndexOutOfRange { array: 'A', index: -1, size: 20 })]
+    );
+
+    let found = lints("10 DIM B(3)\n20 LET B(3) = B(-2)\n30 END\n");
+    assert_eq!(
+        found,
+        [
+            warning(Some(20), Warning::IndexOutOfRange { array: 'B', index: 3, size: 3 }),
+            warning(Some(20), Warning::IndexOutOfRange { array: 'B', index: -2, size: 3 }),
+        ]
+    );
+}
+
+#[test]
+fn json_output_lists_line_code_and_message() {
+    let found = lints("10 GOTO 20\n20 PRINT X\n");
+    assert_eq!(
+        lints_to_json(&found),
+        "[{\"line\": 20, \"code\": \"never-assigned\", \"message\": \"Variable X is read but never assigned\"}, \
+         {\"line\": null, \"code\": \"missing-end\", \"message\": \"Program has no END statement\"}]"
+    );
+}
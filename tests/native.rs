@@ -0,0 +1,41 @@
+//! Host-registered native functions called from BASIC
+
+use rust_basic::console::CapturedOutput;
+use rust_basic::{Interpreter, Program, RuntimeError, Value, ValueType};
+
+fn interpreter(source: &str) -> (Interpreter, CapturedOutput) {
+    let output = CapturedOutput::new();
+    let mut interp = Interpreter::new(Program::parse(source).unwrap());
+    interp.set_output(output.clone());
+    interp.register_function("TEMP", &[ValueType::Int], ValueType::Int, |args| match args {
+        [Value::Int(sensor)] if *sensor >= 0 => Ok(Value::Int(20 + sensor)),
+        _ => Err("no such sensor".to_string()),
+    });
+    interp.register_function("LOG$", &[ValueType::Str], ValueType::Str, |args| {
+        Ok(Value::Str(format!("[log] {}", args[0])))
+    });
+    (interp, output)
+}
+
+#[test]
+fn native_functions_are_called_with_checked_arguments() {
+    let (mut interp, output) = interpreter("10 LET T = temp(3) * 2\n20 PRINT LOG$(\"hot\"), T\n");
+    interp.run().unwrap();
+    assert_eq!(output.contents(), "[log] hot 46\n");
+}
+
+#[test]
+fn native_function_errors_are_runtime_errors() {
+    let cases = [
+        ("10 PRINT TEMP(-1)\n", "TEMP: no such sensor"),
+        ("10 PRINT TEMP(1, 2)\n", "TEMP expects 1 argument(s), got 2"),
+        ("10 PRINT TEMP(\"x\")\n", "Type mismatch: expected number, got string"),
+        ("10 LET X = LOG$(\"x\")\n", "Type mismatch: expected number, got string"),
+        ("10 PRINT NOPE(1)\n", "Undefined function: NOPE"),
+    ];
+    for (source, message) in cases {
+        let (mut interp, _) = interpreter(source);
+        let err: RuntimeError = interp.run().unwrap_err().error;
+        assert_eq!(err.to_string(), message, "{}", source);
+    }
+}
@@ -0,0 +1,79 @@
+//! The optimizer folds constants without changing what a program does
+
+use rust_basic::console::CapturedOutput;
+use rust_basic::{format_line, optimize, Interpreter, Program, RunError, Value, ValueType};
+use std::fs;
+use std::path::Path;
+
+fn optimized(source: &str) -> Vec<String> {
+    optimize(&Program::parse(source).unwrap()).lines().iter().map(format_line).collect()
+}
+
+/// Run `program` once optimized and once not, returning the output and
+/// result of each. `TAG$(n)` is a native function returning a string.
+fn both_ways(program: Program) -> [(String, Result<String, RunError>); 2] {
+    [optimize(&program), program].map(|program| {
+        let output = CapturedOutput::new();
+        let mut interp = Interpreter::new(program);
+        interp.set_output(output.clone());
+        interp.register_function("TAG$", &[ValueType::Int], ValueType::Str, |args| Ok(Value::Str(format!("<{}>", args[0]))));
+        let result = interp.run().map(|status| format!("{:?}", status));
+        (output.contents(), result)
+    })
+}
+
+#[test]
+fn folds_constants_and_identities() {
+    assert_eq!(
+        optimized(
+            "10 LET Y = 2 * 3 + X\n20 LET Y = -(5)\n30 PRINT X * 1 + 0, 0 - X, - -5\n\
+             40 IF 1 + 1 = 2 THEN 10\n50 LET A(4 / 2) = ARGV(1 * 1)\n"
+        ),
+        [
+            "10 LET Y = 6 + X",
+            "20 LET Y = -5",
+            "30 PRINT X, -X, 5",
+            "40 IF 1 THEN 10",
+            "50 LET A(2) = ARGV(1)",
+        ]
+    );
+}
+
+#[test]
+fn leaves_anything_that_can_fail() {
+    assert_eq!(
+        optimized("10 LET X = 5 / 0\n20 LET X = A(9) * 0\n30 LET X = 2147483647 + 1\n40 LET X = Y * 0\n50 LET X = - -Y\n"),
+        // `- -Y` overflows when Y is the smallest i32, just as unoptimized
+        ["10 LET X = 5 / 0", "20 LET X = A(9) * 0", "30 LET X = 2147483647 + 1", "40 LET X = 0", "50 LET X = --Y"]
+    );
+}
+
+#[test]
+fn errors_are_raised_on_the_same_line() {
+    for source in ["10 LET X = 1\n20 LET Y = 2 * 3 / (1 - 1)\n30 END\n", "10 DIM A(3)\n20 PRINT 1 + 1, A(5) * 0\n"] {
+        let [(_, folded), (_, unfolded)] = both_ways(Program::parse(source).unwrap());
+        let (folded, unfolded) = (folded.unwrap_err(), unfolded.unwrap_err());
+        assert_eq!(folded.error.to_string(), unfolded.error.to_string(), "{}", source);
+        assert_eq!((folded.line, unfolded.line), (Some(20), Some(20)), "{}", source);
+    }
+}
+
+#[test]
+fn type_errors_are_not_folded_away() {
+    for source in ["10 PRINT TAG$(1) * 1\n", "10 PRINT 0 + \"ab\"\n", "10 LET X = TAG$(2) - 0\n"] {
+        let [folded, unfolded] = both_ways(Program::parse(source).unwrap());
+        let (folded, unfolded) = (folded.1.unwrap_err(), unfolded.1.unwrap_err());
+        assert_eq!(folded.error.to_string(), unfolded.error.to_string(), "{}", source);
+        assert!(folded.error.to_string().starts_with("Type mismatch"), "{}", folded);
+    }
+}
+
+#[test]
+fn sample_programs_behave_the_same() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    for file in ["sample.bas", "arrays.bas", "GARY", "tests/programs/precedence.bas"] {
+        let [folded, unfolded] = both_ways(Program::parse(&fs::read_to_string(root.join(file)).unwrap()).unwrap());
+        assert_eq!(folded.0, unfolded.0, "{}", file);
+        assert_eq!(folded.1.unwrap(), unfolded.1.unwrap(), "{}", file);
+    }
+}
@@ -0,0 +1,84 @@
+//! Parse errors point at the offending line and column in BASIC terms
+
+use rust_basic::{parse_immediate, parse_recovering, ParseError, Program};
+
+fn report(source: &str) -> String {
+    Program::parse(source).unwrap_err().report(Some("prog.bas"), source)
+}
+
+#[test]
+fn errors_show_location_and_caret() {
+    assert_eq!(
+        report("10 PRINT \"hi\"\n20 LET X 5\n30 END\n"),
+        "prog.bas:2:10: Expected '=', found number 5\n 2 | 20 LET X 5\n   |          ^"
+    );
+    assert_eq!(
+        report("10 PRINT 1\n20 IF X > 1 THEN\n"),
+        "prog.bas:2:17: Expected a line number, found end of line\n 2 | 20 IF X > 1 THEN\n   |                 ^"
+    );
+}
+
+#[test]
+fn messages_name_tokens_in_basic_terms() {
+    let cases = [
+        ("10 GOTO X\n", "Expected a line number, found variable X"),
+        ("10 LET 5 = 1\n", "Expected a variable, found number 5"),
+        ("10 PRINT (1 + 2\n20 END\n", "Expected ')', found end of line"),
+        ("10 FROB 1\n", "Unknown statement: FROB"),
+        ("10 LET X = 1 ? 2\n", "Unexpected character '?'"),
+    ];
+    for (source, message) in cases {
+        assert_eq!(Program::parse(source).unwrap_err().to_string(), message, "{}", source);
+    }
+}
+
+#[test]
+fn lexer_errors_have_spans() {
+    let err = Program::parse("10 PRINT 1\n20 LET X = 99999999999\n").unwrap_err();
+    assert!(matches!(err, ParseError::Lexer(_)));
+    let span = err.span();
+    assert_eq!((span.line, span.column, span.len), (2, 12, 11));
+}
+
+#[test]
+fn recovery_reports_every_broken_line() {
+    let (lines, broken) = parse_recovering("10 PRINT 1\n20 LET X 5\n30 PRINT \"x\n40 GOTO X\n50 END\nfoo\n");
+    let numbers: Vec<i32> = lines.iter().map(|l| l.number).collect();
+    assert_eq!(numbers, [10, 50]);
+
+    let found: Vec<(Option<i32>, &str, String)> =
+        broken.iter().map(|b| (b.number, b.text.as_str(), b.error.to_string())).collect();
+    assert_eq!(
+        found,
+        [
+            (Some(20), "20 LET X 5", "Expected '=', found number 5".to_string()),
+            (Some(30), "30 PRINT \"x", "Unterminated string".to_string()),
+            (Some(40), "40 GOTO X", "Expected a line number, found variable X".to_string()),
+            (None, "foo", "Expected a line number, found FOO".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn leftover_tokens_after_a_statement_are_errors() {
+    let cases = [
+        ("10 PRINT X Y\n", "prog.bas:1:12: Expected end of line, found variable Y\n 1 | 10 PRINT X Y\n   |            ^"),
+        ("10 PRINT 1\n20 LET X = 1 2\n", "prog.bas:2:14: Expected end of line, found number 2\n 2 | 20 LET X = 1 2\n   |              ^"),
+        ("10 END\n\nPRINT 1\n", "prog.bas:3:1: Expected a line number, found PRINT\n 3 | PRINT 1\n   | ^^^^^"),
+    ];
+    for (source, expected) in cases {
+        assert_eq!(report(source), expected, "{}", source);
+    }
+    assert!(Program::parse("\n10 PRINT 1\r\n\n20 END").is_ok());
+}
+
+#[test]
+fn immediate_statements_are_one_line() {
+    assert!(parse_immediate("PRINT 1\n\n").is_ok());
+    let source = "PRINT 1\nPRINT 2";
+    let error = parse_immediate(source).unwrap_err();
+    assert_eq!(
+        error.report(None, source),
+        "Expected end of input, found PRINT\n 2 | PRINT 2\n   | ^^^^^"
+    );
+}
@@ -0,0 +1,7 @@
+10 LET X = -5
+20 LET Y = (X + 2) * 3 - -X
+30 LET Z = X - (Y - 1) + Y * (2 / X)
+40 IF (X < Y) = 1 THEN 60
+50 PRINT -(X * 2), 2 * -X, --X, X - 1 - 2
+60 PRINT "done", Z
+70 PRINT
@@ -0,0 +1,160 @@
+//! Commands typed at the REPL prompt
+
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// Type `commands` into the REPL; returns what it printed after the banner
+/// on stdout, and on stderr
+fn repl(commands: &str) -> (String, String) {
+    repl_with_home(commands, &std::env::temp_dir())
+}
+
+/// `repl` with `home` as the home directory, where history would be saved
+fn repl_with_home(commands: &str, home: &Path) -> (String, String) {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rust-basic"))
+        .arg("repl")
+        .env("HOME", home)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to start interpreter");
+    child.stdin.take().unwrap().write_all(commands.as_bytes()).unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let stdout = stdout.splitn(4, '\n').nth(3).unwrap_or("").to_string();
+    (stdout, String::from_utf8(output.stderr).unwrap())
+}
+
+const PROGRAM: &str = "10 PRINT 1\n20 GOTO 30\n30 END\n";
+
+#[test]
+fn renum_rewrites_jump_targets() {
+    let (out, err) = repl(&format!("{}RENUM 100, , 5\nLIST\n", PROGRAM));
+    assert_eq!(out, "Renumbered 3 lines.\n100 PRINT 1\n105 GOTO 110\n110 END\n");
+    assert_eq!(err, "");
+}
+
+#[test]
+fn renum_from_a_line_keeps_the_earlier_ones() {
+    let (out, _) = repl(&format!("{}RENUM 200, 20\nLIST\n", PROGRAM));
+    assert_eq!(out, "Renumbered 3 lines.\n10 PRINT 1\n200 GOTO 210\n210 END\n");
+}
+
+#[test]
+fn renum_refuses_without_changing_anything() {
+    // Overlapping the kept lines, a missing jump target, running out of numbers
+    let (out, err) = repl(&format!("{}RENUM 5, 20\nRENUM 2147483640\nLIST\n", PROGRAM));
+    assert_eq!(out, PROGRAM);
+    assert_eq!(err, "RENUM failed: new numbers would overlap line 10\nRENUM failed: line numbers would overflow\n");
+
+    let (out, err) = repl("10 GOTO 99\nRENUM\nLIST\n");
+    assert_eq!(out, "10 GOTO 99\n");
+    assert_eq!(err, "RENUM failed: Undefined line 99 in line 10\n");
+}
+
+#[test]
+fn bad_renum_arguments_show_usage() {
+    let (out, err) = repl("RENUM 0, , 0\nRENUM 1, 2, 3, 4\nRENUM X\n");
+    assert_eq!(out, "");
+    assert_eq!(err, "Usage: RENUM [new_start[, old_start[, increment]]]\n".repeat(3));
+}
+
+#[test]
+fn delete_ranges() {
+    let (out, err) = repl(&format!("{}DELETE 15\nDELETE 20-\nDELETE\nLIST\n", PROGRAM));
+    assert_eq!(out, "Deleted 0 lines.\nDeleted 2 lines.\n10 PRINT 1\n");
+    assert_eq!(err, "Usage: DELETE line | DELETE start-end\n");
+}
+
+#[test]
+fn a_bare_line_number_deletes_that_line() {
+    let (out, err) = repl(&format!("{}20\n25\nLIST\n", PROGRAM));
+    assert_eq!(out, "10 PRINT 1\n30 END\n");
+    assert_eq!(err, "Line 25 not found\n");
+}
+
+#[test]
+fn auto_numbers_lines_until_an_empty_one() {
+    let (out, _) = repl("AUTO 100, 5\nPRINT 1\nPRINT 2\n\nLIST\n");
+    assert_eq!(out, "100 PRINT 1\n105 PRINT 2\n");
+}
+
+#[test]
+fn bad_auto_arguments_show_usage() {
+    let (out, err) = repl("AUTO 1, 2, 3\nAUTO -5\n");
+    assert_eq!(out, "");
+    assert_eq!(err, "Usage: AUTO [start[, step]]\n".repeat(2));
+}
+
+#[test]
+fn list_ranges() {
+    let (out, err) = repl(&format!("{}LIST -20\nLIST 30-\nLIST 20\nLIST 30-20\nLIST 1-X\n", PROGRAM));
+    assert_eq!(out, "10 PRINT 1\n20 GOTO 30\n30 END\n20 GOTO 30\n");
+    assert_eq!(err, "Usage: LIST [start-end]\n".repeat(2));
+}
+
+#[test]
+fn list_does_not_page_when_piped() {
+    let program: String = (1..=45).map(|n| format!("{} PRINT {}\n", n, n)).collect();
+    let (out, _) = repl(&format!("{}LIST\n", program));
+    assert_eq!(out, program);
+}
+
+#[test]
+fn find_text_and_jump_targets() {
+    let program = "10 PRINT \"hello\"\n20 GOTO 10\n30 IF A THEN 10\n";
+    let (out, err) = repl(&format!("{}FIND \"HEL\"\nFIND goto 10\nFIND \"zz\"\nFIND GOTO x\n", program));
+    assert_eq!(out, "10 PRINT \"hello\"\n20 GOTO 10\n30 IF A THEN 10\n(No matching lines)\n");
+    assert_eq!(err, "Usage: FIND \"text\" | FIND GOTO line\n");
+}
+
+#[test]
+fn cont_resumes_a_stopped_program() {
+    let (out, err) = repl("10 PRINT 1\n20 STOP\n30 PRINT A\nRUN\nLET A = 7\nCONT\nCONT\n");
+    assert_eq!(out, "1\nStopped at line 20\n7\n");
+    assert_eq!(err, "Can't continue\n");
+}
+
+#[test]
+fn editing_the_program_prevents_cont() {
+    let (out, err) = repl("CONT\n10 PRINT 1\n20 STOP\n30 PRINT 2\nRUN\n30 PRINT 3\nCONT\n");
+    assert_eq!(out, "1\nStopped at line 20\n");
+    assert_eq!(err, "Can't continue\n".repeat(2));
+}
+
+#[test]
+fn statements_without_a_number_run_immediately() {
+    let (out, err) = repl("LET A = 5\nPRINT A * 2\nPRINT 1 / 0\nLIST\n");
+    assert_eq!(out, "10\n(No program)\n");
+    assert_eq!(err, "Runtime error: Division by zero\n");
+}
+
+#[test]
+fn immediate_statements_share_variables_with_the_program() {
+    // GOTO starts the program without clearing A; B is still set after STOP
+    let (out, _) = repl("10 LET B = A * 2\n20 STOP\n30 PRINT B\nLET A = 4\nGOTO 10\nPRINT B\nLET B = 1\nCONT\n");
+    assert_eq!(out, "Stopped at line 20\n8\n1\n");
+}
+
+#[test]
+fn edit_reads_the_replacement_line() {
+    // Piped input can't be pre-filled, so the next line replaces the edited one
+    let (out, err) = repl(&format!("{}EDIT 20\n20 GOTO 10\nEDIT 99\nEDIT X\nLIST\n", PROGRAM));
+    assert_eq!(out, "10 PRINT 1\n20 GOTO 10\n30 END\n");
+    assert_eq!(err, "Line 99 not found\nUsage: EDIT line\n");
+}
+
+#[test]
+fn piped_sessions_do_not_write_history() {
+    let home = std::env::temp_dir().join(format!("rust-basic-home-{}", std::process::id()));
+    fs::create_dir_all(&home).unwrap();
+    let (out, _) = repl_with_home("PRINT 1\n", &home);
+    let written = home.join(".rust_basic_history").exists();
+    fs::remove_dir_all(&home).ok();
+    assert_eq!(out, "1\n");
+    assert!(!written);
+}
@@ -0,0 +1,92 @@
+//! LOAD followed by SAVE must reproduce a program byte for byte
+
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// Drive the REPL: load `input`, save it again and return the saved text
+fn load_and_save(input: &Path) -> String {
+    load_and_save_with(input, "")
+}
+
+/// Like `load_and_save`, with `options` appended to the LOAD command
+fn load_and_save_with(input: &Path, options: &str) -> String {
+    let output = std::env::temp_dir().join(format!(
+        "rust-basic-roundtrip-{}-{}",
+        std::process::id(),
+        input.file_name().unwrap().to_string_lossy()
+    ));
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rust-basic"))
+        .arg("repl")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("failed to start interpreter");
+    write!(
+        child.stdin.take().unwrap(),
+        "LOAD {}{}\nSAVE {}\nQUIT\n",
+        input.display(),
+        options,
+        output.display()
+    )
+    .unwrap();
+    assert!(child.wait().unwrap().success());
+
+    let saved = fs::read_to_string(&output).expect("program was not saved");
+    fs::remove_file(&output).ok();
+    saved
+}
+
+fn assert_round_trip(path: PathBuf) {
+    let original = fs::read_to_string(&path).unwrap();
+    assert_eq!(load_and_save(&path), original, "{} changed on LOAD/SAVE", path.display());
+}
+
+#[test]
+fn sample_programs_round_trip() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    assert_round_trip(root.join("sample.bas"));
+    assert_round_trip(root.join("arrays.bas"));
+}
+
+#[test]
+fn precedence_and_negation_round_trip() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    assert_round_trip(root.join("tests/programs/precedence.bas"));
+}
+
+#[test]
+fn redundant_parentheses_are_dropped_once() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let normalized = load_and_save(&root.join("GARY"));
+    assert_eq!(
+        normalized,
+        "10 LET I = 1\n20 PRINT I\n30 LET I = I + 1\n40 IF I <= 10 THEN 20\n50 END\n"
+    );
+
+    let again = std::env::temp_dir().join(format!("rust-basic-roundtrip-{}-GARY2", std::process::id()));
+    fs::write(&again, &normalized).unwrap();
+    assert_round_trip(again.clone());
+    fs::remove_file(again).ok();
+}
+
+#[test]
+fn broken_lines_kept_by_load_survive_save() {
+    let source = "10 PRINT \"hi\"\n20 LET X 5\n30 PRINT 1 ? 2\n40 END\n";
+    let path = std::env::temp_dir().join(format!("rust-basic-roundtrip-{}-broken", std::process::id()));
+    fs::write(&path, source).unwrap();
+    assert_eq!(load_and_save_with(&path, ", KEEP"), source);
+    fs::remove_file(path).ok();
+}
+
+#[test]
+fn shebang_line_survives_save() {
+    let source = "#!/usr/bin/env rust-basic\n10 PRINT ARGC\n20 END\n";
+    let path = std::env::temp_dir().join(format!("rust-basic-roundtrip-{}-shebang", std::process::id()));
+    fs::write(&path, source).unwrap();
+    assert_round_trip(path.clone());
+    fs::remove_file(path).ok();
+}
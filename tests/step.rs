@@ -0,0 +1,86 @@
+//! Single-stepping, waiting for input and line hooks
+
+use rust_basic::console::{CapturedOutput, InputQueue};
+use rust_basic::{Engine, Interpreter, Program, RunStatus, StepStatus};
+use std::sync::{Arc, Mutex};
+
+#[test]
+fn step_waits_for_queued_input() {
+    let output = CapturedOutput::new();
+    let input = InputQueue::new();
+    let mut interp = Interpreter::new(Program::parse("10 INPUT A\n20 PRINT A * 2\n30 END\n").unwrap());
+    interp.set_output(output.clone());
+    interp.set_input(input.clone());
+
+    assert!(matches!(interp.step(), StepStatus::WaitingForInput(10)));
+    assert!(matches!(interp.step(), StepStatus::WaitingForInput(10)));
+    assert_eq!(interp.current_line(), Some(10));
+
+    input.push_line("21");
+    assert!(matches!(interp.step(), StepStatus::Continue));
+    assert!(matches!(interp.step(), StepStatus::Continue));
+    assert!(matches!(interp.step(), StepStatus::Ended));
+    assert_eq!(output.contents(), "42\n");
+}
+
+#[test]
+fn step_reports_errors_as_status() {
+    let mut interp = Interpreter::new(Program::parse("10 LET X = 1 / 0\n").unwrap());
+    match interp.step() {
+        StepStatus::Error(e) => assert_eq!(e.to_string(), "Division by zero in line 10: LET X = 1 / 0"),
+        other => panic!("expected an error, got {:?}", other),
+    }
+}
+
+#[test]
+fn hooks_trace_each_line() {
+    let trace = Arc::new(Mutex::new(Vec::new()));
+    let mut interp =
+        Interpreter::new(Program::parse("10 LET I = 1\n20 LET I = I + 1\n30 IF I < 3 THEN 20\n").unwrap());
+    let before = Arc::clone(&trace);
+    interp.set_before_line_hook(move |_, line| before.lock().unwrap().push(format!("> {}", line.number)));
+    let after = Arc::clone(&trace);
+    interp.set_after_line_hook(move |interp, line| {
+        after
+            .lock()
+            .unwrap()
+            .push(format!("< {} I={}", line.number, interp.variable('I').unwrap()))
+    });
+
+    interp.run().unwrap();
+    assert_eq!(
+        *trace.lock().unwrap(),
+        ["> 10", "< 10 I=1", "> 20", "< 20 I=2", "> 30", "< 30 I=2", "> 20", "< 20 I=3", "> 30", "< 30 I=3"]
+    );
+}
+
+#[test]
+fn run_errors_name_the_failing_line() {
+    let mut interp = Interpreter::new(
+        Program::parse("10 LET Y = 1\n20 LET Z = 0\n30 GOTO 40\n40 LET X = Y / Z\n50 GOTO 99\n").unwrap(),
+    );
+    let err = interp.run().unwrap_err();
+    assert_eq!(err.to_string(), "Division by zero in line 40: LET X = Y / Z");
+    assert_eq!(err.line, Some(40));
+
+    let mut interp = Interpreter::new(Program::parse("10 GOTO 99\n").unwrap());
+    assert_eq!(interp.run().unwrap_err().to_string(), "Invalid line number: 99 in line 10: GOTO 99");
+}
+
+#[test]
+fn run_after_waiting_for_input_starts_afresh() {
+    for engine in [Engine::TreeWalker, Engine::Bytecode] {
+        let trace = Arc::new(Mutex::new(Vec::new()));
+        let mut interp = Interpreter::new(Program::parse("10 PRINT 1\n20 INPUT A\n30 END\n").unwrap());
+        interp.set_engine(engine);
+        interp.set_output(CapturedOutput::new());
+        interp.set_input(InputQueue::new());
+        let hook = Arc::clone(&trace);
+        interp.set_before_line_hook(move |_, line| hook.lock().unwrap().push(line.number));
+
+        assert_eq!(interp.run().unwrap(), RunStatus::WaitingForInput(20));
+        assert_eq!(interp.run().unwrap(), RunStatus::WaitingForInput(20));
+        assert_eq!(*trace.lock().unwrap(), [10, 20, 10, 20], "{:?}", engine);
+        assert_eq!(RunStatus::WaitingForInput(20).to_string(), "Waiting for input in line 20");
+    }
+}